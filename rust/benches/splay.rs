@@ -0,0 +1,44 @@
+/*
+* splay.rs
+*
+* Times bottom-up splay against splay_top_down on the same pseudo-random
+* access sequences, and both against the parent-free CompactSplayTree, whose
+* smaller nodes fit more of the tree in cache. Run with `cargo bench`.
+*/
+
+use std::time::Instant;
+
+use splay_tree::{CompactSplayTree, NodeId, SplayTree};
+
+const N: usize = 100_000;
+const ACCESSES: usize = 1_000_000;
+
+// xorshift, so the benchmark needs no dependencies and is repeatable
+fn accesses(mut seed: u64) -> Vec<usize> {
+    (0..ACCESSES).map(|_| {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        (seed % N as u64) as usize
+    }).collect()
+}
+
+fn time<T>(name: &str, mut tree: T, seq: &[usize], splay: fn(&mut T, usize)) {
+    let start = Instant::now();
+    for &idx in seq {
+        splay(&mut tree, idx);
+    }
+    let elapsed = start.elapsed();
+    println!("{:>10}: {:?} for {} splays ({:?} each)", name, elapsed, seq.len(), elapsed / seq.len() as u32);
+}
+
+fn main() {
+    let seq = accesses(15451);
+    time("bottom-up", SplayTree::new(N), &seq, |t, i| { t.splay(Some(NodeId::from_index(i))); });
+    time("top-down", SplayTree::new(N), &seq, |t, i| { t.splay_top_down(NodeId::from_index(i)); });
+    time("compact", CompactSplayTree::new(N), &seq, |t, i| { t.find(&i); });
+
+    println!("\nnode arena for {} keys:", N);
+    println!("{:>10}: {} bytes", "parents", SplayTree::new(N).memory_bytes());
+    println!("{:>10}: {} bytes", "compact", CompactSplayTree::new(N).memory_bytes());
+}
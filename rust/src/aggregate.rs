@@ -0,0 +1,673 @@
+/*
+* aggregate.rs
+*
+* A splay tree in which every node also keeps an aggregate of the values in
+* its subtree (their sum, minimum, maximum, or any other monoid), maintained
+* through rotations the same way as the subtree size. Combining the values
+* over any key range then takes two splays and a single read, rather than a
+* walk over the range.
+*
+* For monoids that support it (SumMonoid does), add_range adds a delta to
+* every value in a key range. The delta is only applied to the root of the
+* range's subtree and left there as a pending add for its descendants,
+* pushed one level further down whenever a rotation is about to move them,
+* the same way SeqSplay pushes down its reverse flags.
+*/
+
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+use std::ops::Add;
+
+use crate::{LEFT, RIGHT};
+
+/**
+ * An associative way of combining values, with an identity element. The
+ * aggregate of a subtree is its values combined in key order, so combine
+ * need not be commutative.
+ */
+pub trait Monoid {
+    type T: Clone;
+
+    fn identity() -> Self::T;
+    fn combine(a: &Self::T, b: &Self::T) -> Self::T;
+
+    /**
+     * The aggregate of count values after adding delta to each of them,
+     * given their aggregate agg before. Only add_range needs it, so monoids
+     * for which there is no such formula can leave the default, which
+     * panics.
+     */
+    fn add(_agg: &Self::T, _delta: &Self::T, _count: usize) -> Self::T {
+        panic!("this monoid does not support add_range");
+    }
+}
+
+// The least and greatest values of a type, the identities for MaxMonoid and MinMonoid
+pub trait Bounded {
+    const MIN: Self;
+    const MAX: Self;
+}
+
+macro_rules! impl_bounded {
+    ($($t:ty),*) => {
+        $(impl Bounded for $t {
+            const MIN: Self = <$t>::MIN;
+            const MAX: Self = <$t>::MAX;
+        })*
+    };
+}
+
+impl_bounded!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+impl Bounded for f32 {
+    const MIN: Self = f32::NEG_INFINITY;
+    const MAX: Self = f32::INFINITY;
+}
+
+impl Bounded for f64 {
+    const MIN: Self = f64::NEG_INFINITY;
+    const MAX: Self = f64::INFINITY;
+}
+
+// Sums, with V::default() as zero, as it is for every primitive number type
+pub struct SumMonoid<V>(PhantomData<V>);
+
+impl<V: Copy + Default + Add<Output = V>> Monoid for SumMonoid<V> {
+    type T = V;
+
+    fn identity() -> V {
+        V::default()
+    }
+
+    fn combine(a: &V, b: &V) -> V {
+        *a + *b
+    }
+
+    // agg + delta * count, with count multiplied out by doubling since V
+    // need not convert from usize
+    fn add(agg: &V, delta: &V, count: usize) -> V {
+        let (mut sum, mut power, mut n) = (*agg, *delta, count);
+        while n > 0 {
+            if n & 1 == 1 {
+                sum = sum + power;
+            }
+            power = power + power;
+            n >>= 1;
+        }
+        sum
+    }
+}
+
+pub struct MinMonoid<V>(PhantomData<V>);
+
+impl<V: Copy + PartialOrd + Bounded> Monoid for MinMonoid<V> {
+    type T = V;
+
+    fn identity() -> V {
+        V::MAX
+    }
+
+    fn combine(a: &V, b: &V) -> V {
+        if b < a { *b } else { *a }
+    }
+}
+
+pub struct MaxMonoid<V>(PhantomData<V>);
+
+impl<V: Copy + PartialOrd + Bounded> Monoid for MaxMonoid<V> {
+    type T = V;
+
+    fn identity() -> V {
+        V::MIN
+    }
+
+    fn combine(a: &V, b: &V) -> V {
+        if b > a { *b } else { *a }
+    }
+}
+
+struct Node<K, T> {
+    key: K,
+    value: T,
+    parent: Option<usize>,  // None, or Some(Index) into Vec<Nodes>
+    children: [Option<usize>; 2],   // [LEFT] and [RIGHT]: None, or Some(Index) into Vec<Nodes>
+    size: usize,            // number of nodes in the subtree rooted here
+    agg: T,                 // the values in the subtree rooted here, combined in key order
+    add: Option<T>          // delta still to be added to every value below this node
+}
+
+pub struct AggSplayTree<K, M: Monoid> {
+    root: Option<usize>,    // None for an empty tree, or Some(Index) of root Node
+    nodes: Vec<Node<K, M::T>>   // vector of nodes, all of them live
+}
+
+// A tree of numbers keeping the sum of each subtree, see range_sum
+pub type SumSplayTree<K, V> = AggSplayTree<K, SumMonoid<V>>;
+
+impl<K: Clone, M: Monoid> Clone for AggSplayTree<K, M> {
+    fn clone(&self) -> Self {
+        let nodes = self.nodes.iter().map(|n| Node{
+            key: n.key.clone(),
+            value: n.value.clone(),
+            parent: n.parent,
+            children: n.children,
+            size: n.size,
+            agg: n.agg.clone(),
+            add: n.add.clone()
+        }).collect();
+        Self { root: self.root, nodes }
+    }
+}
+
+impl<K: Ord, M: Monoid> AggSplayTree<K, M> {
+
+    // Builds a path over strictly increasing keys, like SplayTree::from_sorted_pairs
+    pub fn from_sorted_pairs(pairs: Vec<(K, M::T)>) -> Self {
+        debug_assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0), "keys must be strictly increasing");
+
+        let mut tree = Self { root: None, nodes: Vec::with_capacity(pairs.len()) };
+        for (key, value) in pairs {
+            let i = tree.nodes.len();
+            let below = tree.root;
+            let agg = M::combine(&tree.subtree_agg(below), &value);
+            tree.nodes.push(Node{ key, value, parent: None, children: [below, None], size: i + 1, agg, add: None });
+            if let Some(b) = below {
+                tree.nodes[b].parent = Some(i);
+            }
+            tree.root = Some(i);
+        }
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn key(&self, idx: usize) -> &K {
+        &self.nodes[idx].key
+    }
+
+    // Value at the node at idx, with any adds still pending above it applied
+    pub fn get(&self, idx: usize) -> M::T {
+        let mut value = self.nodes[idx].value.clone();
+        let mut cur = self.nodes[idx].parent;
+        while let Some(p) = cur {
+            if let Some(delta) = &self.nodes[p].add {
+                value = M::add(&value, delta, 1);
+            }
+            cur = self.nodes[p].parent;
+        }
+        value
+    }
+
+    // Searches for key and splays the node the search stops at, as
+    // SplayTree::find does. Returns key's index, or None if it is absent.
+    pub fn find(&mut self, key: &K) -> Option<usize> {
+        let (idx, ord) = self.descend(key)?;
+        self.splay_to(idx, None);
+        match ord {
+            Ordering::Equal => Some(idx),
+            _ => None
+        }
+    }
+
+    // Inserts key with value, or replaces the value if key is present, and
+    // returns key's index, which ends up at the root
+    pub fn insert(&mut self, key: K, value: M::T) -> usize {
+        let r = match self.descend(&key) {
+            None => {
+                let agg = value.clone();
+                self.nodes.push(Node{ key, value, parent: None, children: [None, None], size: 1, agg, add: None });
+                self.root = Some(0);
+                return 0;
+            },
+            Some((r, Ordering::Equal)) => {
+                self.splay_to(r, None);
+                self.push_down(r);
+                self.nodes[r].value = value;
+                self.update(r);
+                return r;
+            },
+            Some((r, _)) => r
+        };
+        self.splay_to(r, None);
+        self.push_down(r);
+
+        // the new node takes r's subtree on the side key falls, and r on the other
+        let dir = if key < self.nodes[r].key { LEFT } else { RIGHT };
+        let idx = self.nodes.len();
+        let agg = value.clone();
+        self.nodes.push(Node{ key, value, parent: None, children: [None, None], size: 1, agg, add: None });
+        let a_idx = self.nodes[r].children[dir].take();
+        self.set_child(idx, dir, a_idx);
+        self.set_child(idx, 1 - dir, Some(r));
+        self.update(r);
+        self.update(idx);
+        self.root = Some(idx);
+        idx
+    }
+
+    /**
+     * The values whose keys are in [lo, hi) combined in key order, or the
+     * identity for an empty range, in O(log n) amortized.
+     *
+     * The largest key below lo is splayed to the root and the smallest key
+     * at or above hi to the root's right child, which leaves exactly the
+     * range in the subtree between them, whose aggregate is already kept:
+     *
+     * ```text
+     *       < lo
+     *           \
+     *           >= hi
+     *           /
+     *     [lo, hi)
+     * ```
+     *
+     * Without a key below lo (or at or above hi), that side is open and the
+     * range is the rest of the tree on the other side.
+     */
+    pub fn query_range(&mut self, lo: &K, hi: &K) -> M::T {
+        if lo >= hi {
+            return M::identity();
+        }
+        let range = self.isolate(lo, hi);
+        self.subtree_agg(range)
+    }
+
+    /**
+     * Adds delta to every value whose key is in [lo, hi), in O(log n)
+     * amortized. The range is isolated as in query_range, and only the root
+     * of its subtree takes the delta at once; the rest of the subtree keeps
+     * it as a pending add, pushed down as splays reach into it. Panics if
+     * the monoid does not implement Monoid::add.
+     */
+    pub fn add_range(&mut self, lo: &K, hi: &K, delta: M::T) {
+        if lo >= hi {
+            return;
+        }
+        if let Some(range) = self.isolate(lo, hi) {
+            self.apply_add(range, &delta);
+            // the range's aggregate changed under the one or two nodes above it
+            let mut cur = self.nodes[range].parent;
+            while let Some(p) = cur {
+                self.update(p);
+                cur = self.nodes[p].parent;
+            }
+        }
+    }
+
+    // Splays the nodes around [lo, hi) as described at query_range, and
+    // returns the root of the subtree holding exactly the range, with no
+    // adds pending above it
+    fn isolate(&mut self, lo: &K, hi: &K) -> Option<usize> {
+        self.root?;
+        let before = self.last_below(lo);
+        if let Some(a) = before {
+            self.splay_to(a, None);
+            self.push_down(a);
+        }
+        let after = self.first_at_or_above(hi, before);
+        if let Some(b) = after {
+            self.splay_to(b, before);
+            self.push_down(b);
+        }
+
+        match (before, after) {
+            (_, Some(b)) => self.nodes[b].children[LEFT],
+            (Some(a), None) => self.nodes[a].children[RIGHT],
+            (None, None) => self.root
+        }
+    }
+
+    /**
+     * Checks the tree structure: parent and child links agree, every node is
+     * reachable from the root exactly once, keys increase along the inorder
+     * traversal, and every size and aggregate matches its children's.
+     */
+    pub fn check_invariants(&self) -> Result<(), String> where M::T: PartialEq {
+        let n = self.nodes.len();
+        let mut seen = vec![false; n];
+        let mut stack: Vec<(usize, Option<usize>)> = self.root.map(|r| (r, None)).into_iter().collect();
+        while let Some((i, parent)) = stack.pop() {
+            if i >= n || seen[i] {
+                return Err(format!("node {} is out of range or reachable twice", i));
+            }
+            seen[i] = true;
+            let node = &self.nodes[i];
+            if node.parent != parent {
+                return Err(format!("node {} has parent {:?} but is a child of {:?}", i, node.parent, parent));
+            }
+            let [l, r] = node.children;
+            if l.is_some_and(|l| l < n && self.nodes[l].key >= node.key) || r.is_some_and(|r| r < n && self.nodes[r].key <= node.key) {
+                return Err(format!("keys around node {} are out of order", i));
+            }
+            if node.size != 1 + self.subtree_size(l) + self.subtree_size(r) {
+                return Err(format!("node {} has size {} but its children's sizes do not add up", i, node.size));
+            }
+            let [l_agg, r_agg] = node.children.map(|c| {
+                let agg = self.subtree_agg(c);
+                match &node.add {
+                    Some(delta) => M::add(&agg, delta, self.subtree_size(c)),
+                    None => agg
+                }
+            });
+            if node.agg != M::combine(&M::combine(&l_agg, &node.value), &r_agg) {
+                return Err(format!("node {} has an aggregate that does not match its subtree", i));
+            }
+            stack.extend(node.children.iter().flatten().map(|&c| (c, Some(i))));
+        }
+        match (0..n).find(|&i| !seen[i]) {
+            Some(i) => Err(format!("node {} is not reachable from the root", i)),
+            None => Ok(())
+        }
+    }
+
+    // Walks down from the root looking for key, as SplayTree::descend does
+    fn descend(&self, key: &K) -> Option<(usize, Ordering)> {
+        let mut cur = self.root?;
+        loop {
+            let node = &self.nodes[cur];
+            let ord = key.cmp(&node.key);
+            let next = match ord {
+                Ordering::Less => node.children[LEFT],
+                Ordering::Greater => node.children[RIGHT],
+                Ordering::Equal => None
+            };
+            match next {
+                None => return Some((cur, ord)),
+                Some(i) => cur = i
+            }
+        }
+    }
+
+    // Index of the largest key < key, without splaying
+    fn last_below(&self, key: &K) -> Option<usize> {
+        let mut best = None;
+        let mut cur = self.root;
+        while let Some(i) = cur {
+            if self.nodes[i].key < *key {
+                best = Some(i);
+                cur = self.nodes[i].children[RIGHT];
+            }
+            else {
+                cur = self.nodes[i].children[LEFT];
+            }
+        }
+        best
+    }
+
+    // Index of the smallest key >= key below start (the whole tree for None),
+    // without splaying
+    fn first_at_or_above(&self, key: &K, start: Option<usize>) -> Option<usize> {
+        let mut best = None;
+        let mut cur = match start {
+            None => self.root,
+            Some(s) => self.nodes[s].children[RIGHT]
+        };
+        while let Some(i) = cur {
+            if self.nodes[i].key >= *key {
+                best = Some(i);
+                cur = self.nodes[i].children[LEFT];
+            }
+            else {
+                cur = self.nodes[i].children[RIGHT];
+            }
+        }
+        best
+    }
+}
+
+impl<K: Ord, V: Copy + Default + Add<Output = V>> SumSplayTree<K, V> {
+
+    // Sum of the values whose keys are in [lo, hi); see query_range
+    pub fn range_sum(&mut self, lo: &K, hi: &K) -> V {
+        self.query_range(lo, hi)
+    }
+}
+
+impl<K, M: Monoid> AggSplayTree<K, M> {
+
+    fn subtree_size(&self, idx: Option<usize>) -> usize {
+        idx.map_or(0, |i| self.nodes[i].size)
+    }
+
+    fn subtree_agg(&self, idx: Option<usize>) -> M::T {
+        idx.map_or_else(M::identity, |i| self.nodes[i].agg.clone())
+    }
+
+    // idx's left aggregate, own value, and right aggregate, combined in that order
+    fn combined(&self, idx: usize) -> M::T {
+        let [l, r] = self.nodes[idx].children;
+        M::combine(&M::combine(&self.subtree_agg(l), &self.nodes[idx].value), &self.subtree_agg(r))
+    }
+
+    // Adds delta to the value and aggregate at idx, and to what is pending for its subtree
+    fn apply_add(&mut self, idx: usize, delta: &M::T) {
+        let node = &mut self.nodes[idx];
+        node.value = M::add(&node.value, delta, 1);
+        node.agg = M::add(&node.agg, delta, node.size);
+        node.add = Some(match &node.add {
+            None => delta.clone(),
+            Some(pending) => M::add(pending, delta, 1)
+        });
+    }
+
+    // Hands idx's pending add on to its children, after which their values
+    // and aggregates are up to date as far as idx is concerned
+    fn push_down(&mut self, idx: usize) {
+        if let Some(delta) = self.nodes[idx].add.take() {
+            for c in self.nodes[idx].children.into_iter().flatten() {
+                self.apply_add(c, &delta);
+            }
+        }
+    }
+
+    // Recomputes idx's size and aggregate from its children's, which must
+    // have nothing pending from idx
+    fn update(&mut self, idx: usize) {
+        let [l, r] = self.nodes[idx].children;
+        self.nodes[idx].size = 1 + self.subtree_size(l) + self.subtree_size(r);
+        self.nodes[idx].agg = self.combined(idx);
+    }
+
+    fn set_child(&mut self, idx: usize, dir: usize, child: Option<usize>) {
+        self.nodes[idx].children[dir] = child;
+        if let Some(c) = child {
+            self.nodes[c].parent = Some(idx);
+        }
+    }
+
+    // Which side of its parent the node at idx hangs on
+    fn side(&self, idx: usize) -> usize {
+        let parent = &self.nodes[self.nodes[idx].parent.unwrap()];
+        if parent.children[LEFT] == Some(idx) { LEFT } else { RIGHT }
+    }
+
+    // Lifts y's child on side dir above y, as SplayTree::rotate does, keeping
+    // both sizes and aggregates up to date. Adds pending at y and x are pushed
+    // down first, since the subtrees below them are about to change hands.
+    fn rotate(&mut self, y_idx: usize, dir: usize) {
+        let x_idx = self.nodes[y_idx].children[dir].unwrap();
+        let z_idx = self.nodes[y_idx].parent;
+        self.push_down(y_idx);
+        self.push_down(x_idx);
+        let b_idx = self.nodes[x_idx].children[1 - dir];
+
+        self.set_child(y_idx, dir, b_idx);
+        self.set_child(x_idx, 1 - dir, Some(y_idx));
+        self.update(y_idx);
+        self.update(x_idx);
+
+        match z_idx {
+            None => {
+                self.root = Some(x_idx);
+                self.nodes[x_idx].parent = None;
+            },
+            Some(z) => {
+                let side = if self.nodes[z].children[LEFT] == Some(y_idx) { LEFT } else { RIGHT };
+                self.set_child(z, side, Some(x_idx));
+            }
+        }
+    }
+
+    // Splays x up until its parent is goal (None for the root)
+    fn splay_to(&mut self, x_idx: usize, goal: Option<usize>) {
+        while self.nodes[x_idx].parent != goal {
+            let y_idx = self.nodes[x_idx].parent.unwrap();
+            let x_dir = self.side(x_idx);
+            match self.nodes[y_idx].parent {
+                z if z == goal => self.rotate(y_idx, x_dir),    // zig
+                Some(z_idx) => {
+                    let y_dir = self.side(y_idx);
+                    if x_dir == y_dir {                         // zig-zig
+                        self.rotate(z_idx, y_dir);
+                        self.rotate(y_idx, x_dir);
+                    }
+                    else {                                      // zig-zag
+                        self.rotate(y_idx, x_dir);
+                        self.rotate(z_idx, y_dir);
+                    }
+                },
+                None => unreachable!("goal is not an ancestor")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    type Rand = Box<dyn FnMut(u64) -> u64>;
+
+    fn rand() -> Rand {
+        let mut seed = 0x9e3779b97f4a7c15_u64;
+        Box::new(move |n| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed % n
+        })
+    }
+
+    // Random keys in 0..200 with values in -500..500, in both a tree and a BTreeMap
+    fn random_tree<M: Monoid<T = i64>>(next: &mut Rand) -> (AggSplayTree<u64, M>, BTreeMap<u64, i64>) {
+        let mut tree = AggSplayTree::from_sorted_pairs(vec![]);
+        let mut model = BTreeMap::new();
+        for _ in 0..300 {
+            let (k, v) = (next(200), next(1000) as i64 - 500);
+            tree.insert(k, v);
+            model.insert(k, v);
+        }
+        (tree, model)
+    }
+
+    // Compares query_range on random ranges with folding the model's values
+    fn check_monoid<M: Monoid<T = i64>>(fold: fn(i64, i64) -> i64) {
+        let mut next = rand();
+        let (mut tree, model) = random_tree::<M>(&mut next);
+        assert_eq!(tree.check_invariants(), Ok(()));
+        for _ in 0..300 {
+            let (lo, hi) = (next(210), next(210));
+            let expected = model.range(lo..hi.max(lo)).map(|(_, &v)| v).fold(M::identity(), fold);
+            assert_eq!(tree.query_range(&lo, &hi), expected, "[{}, {})", lo, hi);
+        }
+        assert_eq!(tree.check_invariants(), Ok(()));
+        assert_eq!(tree.len(), model.len());
+    }
+
+    #[test]
+    fn sum_matches_brute_force() {
+        check_monoid::<SumMonoid<i64>>(|a, b| a + b);
+    }
+
+    #[test]
+    fn min_matches_brute_force() {
+        check_monoid::<MinMonoid<i64>>(i64::min);
+    }
+
+    #[test]
+    fn max_matches_brute_force() {
+        check_monoid::<MaxMonoid<i64>>(i64::max);
+    }
+
+    #[test]
+    fn range_sum_on_a_path() {
+        let mut tree = SumSplayTree::from_sorted_pairs((0..10).map(|i| (i, i as f64 * 0.5)).collect());
+        assert_eq!(tree.range_sum(&0, &10), 22.5);
+        assert_eq!(tree.range_sum(&3, &6), 6.0);
+        assert_eq!(tree.range_sum(&9, &20), 4.5);
+        assert_eq!(tree.range_sum(&5, &5), 0.0);
+        let idx = tree.find(&4).unwrap();
+        tree.insert(4, 10.0);
+        assert_eq!(tree.get(idx), 10.0);
+        assert_eq!(tree.range_sum(&3, &6), 14.0);
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn min_and_max_of_empty_range_are_identities() {
+        let mut min: AggSplayTree<i32, MinMonoid<f64>> = AggSplayTree::from_sorted_pairs(vec![(1, 2.0), (2, -1.0)]);
+        assert_eq!(min.query_range(&1, &3), -1.0);
+        assert_eq!(min.query_range(&3, &9), f64::INFINITY);
+        let mut max: AggSplayTree<i32, MaxMonoid<u8>> = AggSplayTree::from_sorted_pairs(vec![(1, 7), (2, 3)]);
+        assert_eq!(max.query_range(&2, &3), 3);
+        assert_eq!(max.query_range(&0, &1), 0);
+    }
+
+    #[test]
+    fn add_range_matches_brute_force() {
+        let mut next = rand();
+        let (mut tree, mut model) = random_tree::<SumMonoid<i64>>(&mut next);
+        for _ in 0..500 {
+            let (lo, hi) = (next(210), next(210));
+            if next(2) == 0 {
+                let delta = next(100) as i64 - 50;
+                tree.add_range(&lo, &hi, delta);
+                for (_, v) in model.range_mut(lo..hi.max(lo)) {
+                    *v += delta;
+                }
+            }
+            else {
+                let expected: i64 = model.range(lo..hi.max(lo)).map(|(_, &v)| v).sum();
+                assert_eq!(tree.range_sum(&lo, &hi), expected, "[{}, {})", lo, hi);
+            }
+            if next(10) == 0 {
+                let (k, v) = (next(200), next(1000) as i64 - 500);
+                tree.insert(k, v);
+                model.insert(k, v);
+            }
+            assert_eq!(tree.check_invariants(), Ok(()));
+        }
+        for (k, &v) in &model {
+            let idx = tree.descend(k).unwrap().0;
+            assert_eq!(tree.get(idx), v, "key {}", k);
+        }
+    }
+
+    #[test]
+    fn add_range_on_a_path() {
+        let mut tree = SumSplayTree::from_sorted_pairs((0..8).map(|i| (i, 1)).collect());
+        tree.add_range(&2, &6, 10);
+        tree.add_range(&4, &20, 100);
+        assert_eq!(tree.range_sum(&0, &8), 8 + 40 + 400);
+        assert_eq!(tree.range_sum(&3, &5), 11 + 111);
+        let idx = tree.descend(&5).unwrap().0;
+        assert_eq!(tree.get(idx), 111);
+        tree.add_range(&9, &20, 1000);
+        assert_eq!(tree.range_sum(&0, &8), 448);
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic(expected = "does not support add_range")]
+    fn add_range_needs_monoid_add() {
+        let mut min: AggSplayTree<i32, MinMonoid<i32>> = AggSplayTree::from_sorted_pairs(vec![(1, 2), (2, 3)]);
+        min.add_range(&1, &3, 1);
+    }
+}
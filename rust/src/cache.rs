@@ -0,0 +1,135 @@
+/*
+* cache.rs
+*
+* A bounded key-value cache on top of SplayTree. Every get and put splays its
+* key to the root, so keys in use stay near the top and keys nobody has asked
+* for in a while sink. When the cache is over capacity it evicts the deepest
+* node, which approximates least-recently-used without any extra bookkeeping:
+* the tree's shape is the recency list.
+*/
+
+use crate::{NodeId, SplayTree, LEFT, RIGHT};
+
+pub struct SplayCache<K, V> {
+    tree: SplayTree<K, V>,
+    capacity: usize     // most entries held at once
+}
+
+impl<K: Ord, V> SplayCache<K, V> {
+
+    // An empty cache holding at most capacity entries. Panics if capacity is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "SplayCache::new: capacity must be at least 1");
+        Self { tree: SplayTree::with_capacity(capacity + 1), capacity }
+    }
+
+    // The value for key, splaying it to the root on a hit. A miss splays
+    // the end of the search path instead, as find does.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let idx = self.tree.find(key)?;
+        Some(self.tree.value(idx))
+    }
+
+    /**
+     * Inserts or updates key, leaving it at the root. If that takes the cache
+     * over capacity, the deepest node is removed and returned. The new key is
+     * at depth 0, so it is never the one evicted.
+     *
+     * Finding the deepest node walks the whole tree, so a put that evicts
+     * costs O(capacity) on top of the O(log n) amortized insert.
+     */
+    pub fn put(&mut self, key: K, value: V) -> Option<(K, V)> {
+        self.tree.insert_pair(key, value);
+        if self.tree.len() <= self.capacity {
+            return None;
+        }
+        let victim = self.deepest()?;
+        self.tree.splay(Some(victim));
+        Some(self.tree.remove_root())
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.tree.get(key).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // The node farthest from the root, the first one found if several tie
+    fn deepest(&self) -> Option<NodeId> {
+        let mut best = (self.tree.root?, 0);
+        let mut stack = vec![best];
+        while let Some((i, depth)) = stack.pop() {
+            if depth > best.1 {
+                best = (i, depth);
+            }
+            let node = &self.tree.nodes[i];
+            stack.extend(node.children[RIGHT].map(|r| (r, depth + 1)));
+            stack.extend(node.children[LEFT].map(|l| (l, depth + 1)));
+        }
+        Some(best.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_and_miss() {
+        let mut cache = SplayCache::new(4);
+        assert_eq!(cache.get(&1), None);
+        cache.put(1, "one");
+        cache.put(2, "two");
+        assert_eq!(cache.get(&1), Some(&"one"));
+        assert_eq!(cache.get(&3), None);
+
+        // a put of a present key updates it and evicts nothing
+        assert_eq!(cache.put(1, "uno"), None);
+        assert_eq!(cache.get(&1), Some(&"uno"));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.tree.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn evicts_the_coldest_key() {
+        let mut cache = SplayCache::new(3);
+        for key in 1..=3 {
+            assert_eq!(cache.put(key, key * 10), None);
+        }
+
+        // inserted in order, 1 is at the bottom of the left path
+        assert_eq!(cache.put(4, 40), Some((1, 10)));
+
+        // touching 2 lifts it to the root, leaving 3 at the bottom
+        assert_eq!(cache.get(&2), Some(&20));
+        assert_eq!(cache.put(5, 50), Some((3, 30)));
+        assert!(cache.contains(&2) && cache.contains(&4) && cache.contains(&5));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.tree.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn capacity_one_keeps_the_newest() {
+        let mut cache = SplayCache::new(1);
+        cache.put("a", 1);
+        assert_eq!(cache.put("b", 2), Some(("a", 1)));
+        assert_eq!(cache.get(&"b"), Some(&2));
+        assert_eq!(cache.capacity(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "capacity must be at least 1")]
+    fn zero_capacity() {
+        SplayCache::<u32, u32>::new(0);
+    }
+}
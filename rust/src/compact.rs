@@ -0,0 +1,258 @@
+/*
+* compact.rs
+*
+* A splay tree without parent pointers. Each node holds only its two child
+* links, a third less link storage per node than SplayTree, at the cost of
+* only being able to splay by key, top-down: without parents there is no way
+* back up from an arbitrary index.
+*/
+
+use std::cmp::Ordering;
+
+#[derive(Clone)]
+struct Node<K, V> {
+    key: K,
+    value: V,
+    left: Option<usize>,    // None, or Some(Index) into Vec<Nodes>
+    right: Option<usize>    // None, or Some(Index) into Vec<Nodes>
+}
+
+#[derive(Clone)]
+pub struct CompactSplayTree<K, V = ()> {
+    root: Option<usize>,    // None for an empty tree, or Some(Index) of root Node
+    nodes: Vec<Node<K, V>>  // vector of nodes, all of them live
+}
+
+impl CompactSplayTree<usize> {
+
+    // Same shape and keys as SplayTree::new(n): a left path over 0..n rooted at n-1
+    pub fn new(n: usize) -> Self {
+        Self::from_sorted_keys((0..n).collect())
+    }
+}
+
+impl<K: Ord> CompactSplayTree<K> {
+
+    pub fn from_sorted_keys(keys: Vec<K>) -> Self {
+        Self::from_sorted_pairs(keys.into_iter().map(|k| (k, ())).collect())
+    }
+}
+
+impl<K: Ord, V> CompactSplayTree<K, V> {
+
+    // Builds the same left path as SplayTree::from_sorted_pairs
+    pub fn from_sorted_pairs(pairs: Vec<(K, V)>) -> Self {
+        debug_assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0), "keys must be strictly increasing");
+
+        let nodes: Vec<Node<K, V>> = pairs.into_iter().enumerate().map(|(i, (key, value))| {
+            Node{ key, value, left: i.checked_sub(1), right: None }
+        }).collect();
+        Self { root: nodes.len().checked_sub(1), nodes }
+    }
+
+    /**
+     * Searches for key and splays the node the search stops at to the root,
+     * the same way SplayTree::splay_top_down does. Returns the index of key's
+     * node, or None if it is absent.
+     */
+    pub fn find(&mut self, key: &K) -> Option<usize> {
+        let t = self.splay(key)?;
+        match key.cmp(&self.nodes[t].key) {
+            Ordering::Equal => Some(t),
+            _ => None
+        }
+    }
+
+    // Top-down splay towards key; returns the new root
+    fn splay(&mut self, key: &K) -> Option<usize> {
+        let mut t = self.root?;
+        let mut left_root = None;
+        let mut left_last: Option<usize> = None;    // rightmost node of the left tree
+        let mut right_root = None;
+        let mut right_last: Option<usize> = None;   // leftmost node of the right tree
+
+        loop {
+            let ord = key.cmp(&self.nodes[t].key);
+            let mut y = match ord {
+                Ordering::Equal => break,
+                Ordering::Less => self.nodes[t].left,
+                Ordering::Greater => self.nodes[t].right
+            };
+            let y_idx = match y {
+                None => break,
+                Some(i) => i
+            };
+
+            // zig-zig: rotate y above t before linking
+            if key.cmp(&self.nodes[y_idx].key) == ord {
+                if ord == Ordering::Less {
+                    self.nodes[t].left = self.nodes[y_idx].right;
+                    self.nodes[y_idx].right = Some(t);
+                }
+                else {
+                    self.nodes[t].right = self.nodes[y_idx].left;
+                    self.nodes[y_idx].left = Some(t);
+                }
+                t = y_idx;
+                y = if ord == Ordering::Less { self.nodes[t].left } else { self.nodes[t].right };
+                if y.is_none() {
+                    break;
+                }
+            }
+
+            // link t into the right tree (key is smaller) or the left tree
+            if ord == Ordering::Less {
+                match right_last {
+                    None => right_root = Some(t),
+                    Some(r) => self.nodes[r].left = Some(t)
+                }
+                right_last = Some(t);
+            }
+            else {
+                match left_last {
+                    None => left_root = Some(t),
+                    Some(l) => self.nodes[l].right = Some(t)
+                }
+                left_last = Some(t);
+            }
+            t = y.unwrap();
+        }
+
+        // reassemble: t's subtrees go on the inner edges, the two trees become its children
+        let a_idx = self.nodes[t].left;
+        let b_idx = self.nodes[t].right;
+        match left_last {
+            None => left_root = a_idx,
+            Some(l) => self.nodes[l].right = a_idx
+        }
+        match right_last {
+            None => right_root = b_idx,
+            Some(r) => self.nodes[r].left = b_idx
+        }
+        self.nodes[t].left = left_root;
+        self.nodes[t].right = right_root;
+        self.root = Some(t);
+        Some(t)
+    }
+
+    /**
+     * Checks the structure of the tree from the child links alone: every
+     * child index is in range, every node is reachable from the root exactly
+     * once, and keys increase strictly along the inorder traversal. The error
+     * names the index of the first offending node found.
+     */
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let n = self.nodes.len();
+        let mut seen = vec![false; n];
+        let mut prev: Option<usize> = None;
+        let mut stack = Vec::new();
+        let mut cur = self.root;
+        while cur.is_some() || !stack.is_empty() {
+            while let Some(i) = cur {
+                if i >= n {
+                    return Err(format!("node index {} out of range", i));
+                }
+                if seen[i] {
+                    return Err(format!("node {} is reachable twice", i));
+                }
+                seen[i] = true;
+                stack.push(i);
+                cur = self.nodes[i].left;
+            }
+            let i = stack.pop().unwrap();
+            if let Some(p) = prev {
+                if self.nodes[p].key >= self.nodes[i].key {
+                    return Err(format!("key of node {} is not greater than its predecessor {}", i, p));
+                }
+            }
+            prev = Some(i);
+            cur = self.nodes[i].right;
+        }
+        match (0..n).find(|&i| !seen[i]) {
+            Some(i) => Err(format!("node {} is not reachable from the root", i)),
+            None => Ok(())
+        }
+    }
+}
+
+impl<K, V> CompactSplayTree<K, V> {
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    // Key stored at the node at idx
+    pub fn key(&self, idx: usize) -> &K {
+        &self.nodes[idx].key
+    }
+
+    // Value stored at the node at idx
+    pub fn get(&self, idx: usize) -> &V {
+        &self.nodes[idx].value
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> &mut V {
+        &mut self.nodes[idx].value
+    }
+
+    // Bytes allocated for the node arena
+    pub fn memory_bytes(&self) -> usize {
+        self.nodes.capacity() * std::mem::size_of::<Node<K, V>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NodeId, SplayTree, LEFT, RIGHT};
+
+    type Links = (Option<usize>, Vec<(Option<usize>, Option<usize>)>);
+
+    fn links<K, V>(tree: &CompactSplayTree<K, V>) -> Links {
+        (tree.root, tree.nodes.iter().map(|n| (n.left, n.right)).collect())
+    }
+
+    fn tree_links<K, V>(tree: &SplayTree<K, V>) -> Links {
+        let index = |i: Option<NodeId>| i.map(NodeId::index);
+        (index(tree.root), tree.nodes.iter().map(|n| (index(n.children[LEFT]), index(n.children[RIGHT]))).collect())
+    }
+
+    #[test]
+    fn find_matches_splay_top_down() {
+        let mut compact = CompactSplayTree::new(50);
+        let mut tree = SplayTree::new(50);
+        for k in [17, 3, 49, 25, 26, 0, 25, 38] {
+            assert_eq!(compact.find(&k), Some(k));
+            tree.splay_top_down(NodeId::from_index(k));
+            assert_eq!(links(&compact), tree_links(&tree));
+            assert_eq!(compact.check_invariants(), Ok(()));
+        }
+        assert_eq!(compact.find(&50), None);
+        assert_eq!(compact.root, Some(49));
+        assert_eq!(CompactSplayTree::new(0).find(&0), None);
+    }
+
+    #[test]
+    fn nodes_are_smaller() {
+        assert!(CompactSplayTree::new(100).memory_bytes() < SplayTree::new(100).memory_bytes());
+    }
+
+    #[test]
+    fn check_invariants_without_parents() {
+        let mut tree = CompactSplayTree::from_sorted_keys(vec!["a", "b", "c", "d"]);
+        tree.find(&"b");
+        assert_eq!(tree.check_invariants(), Ok(()));
+
+        let mut bad = tree.clone();
+        bad.nodes[1].right = None;
+        assert!(bad.check_invariants().unwrap_err().contains("not reachable"));
+
+        let mut bad = tree.clone();
+        bad.nodes[0].right = Some(1);
+        assert_eq!(bad.check_invariants(), Err("node 1 is reachable twice".to_string()));
+    }
+}
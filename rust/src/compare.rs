@@ -0,0 +1,49 @@
+/*
+* compare.rs
+*
+* The order a SplayTree keeps its keys in. By default that is the keys' own
+* Ord; with_comparator takes any Fn(&K, &K) -> Ordering instead, the way
+* slice::sort_by does, for a reverse order or an order by some derived key
+* without wrapping every key in a newtype.
+*/
+
+use std::cmp::Ordering;
+
+use crate::SplayTree;
+
+// An order on keys of type K, which must be a total order for the tree's
+// searches to make sense
+pub trait Compare<K> {
+    fn compare(&self, a: &K, b: &K) -> Ordering;
+}
+
+// The keys' own Ord, used by every constructor except with_comparator
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Natural;
+
+impl<K: Ord> Compare<K> for Natural {
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        a.cmp(b)
+    }
+}
+
+impl<K, F: Fn(&K, &K) -> Ordering> Compare<K> for F {
+    fn compare(&self, a: &K, b: &K) -> Ordering {
+        self(a, b)
+    }
+}
+
+impl<K, V, F: Fn(&K, &K) -> Ordering> SplayTree<K, V, F> {
+
+    /**
+     * An empty tree that orders its keys by cmp instead of Ord, e.g.
+     * |a, b| b.cmp(a) for largest first. Every search, insert, and split
+     * goes through cmp, so min, first, and iteration all follow its order.
+     *
+     * Trees split off this one share a clone of cmp, and join and union
+     * assume both trees use the same order.
+     */
+    pub fn with_comparator(cmp: F) -> Self {
+        Self::empty(0, cmp)
+    }
+}
@@ -0,0 +1,133 @@
+/*
+* entry.rs
+*
+* A view into a single key of a SplayTree, present or not, in the style of
+* BTreeMap::entry. Looking the key up splays it (or its neighbor) to the
+* root, so whatever is done with the entry afterwards starts from the root.
+*/
+
+use crate::{Compare, Natural, NodeId, SplayTree};
+
+pub enum Entry<'a, K, V, C = Natural> {
+    Occupied(OccupiedEntry<'a, K, V, C>),
+    Vacant(VacantEntry<'a, K, V, C>)
+}
+
+// The key is in the tree, at the root
+pub struct OccupiedEntry<'a, K, V, C = Natural> {
+    tree: &'a mut SplayTree<K, V, C>,
+    idx: NodeId
+}
+
+// The key is absent; the root is its predecessor or successor, if any
+pub struct VacantEntry<'a, K, V, C = Natural> {
+    tree: &'a mut SplayTree<K, V, C>,
+    key: K
+}
+
+impl<K, V, C: Compare<K>> SplayTree<K, V, C> {
+
+    // Looks key up once for an insert-or-update
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C> {
+        match self.find(&key) {
+            Some(idx) => Entry::Occupied(OccupiedEntry { tree: self, idx }),
+            None => Entry::Vacant(VacantEntry { tree: self, key })
+        }
+    }
+
+    // The value for key, inserting f() first if key is absent; f is only
+    // called in that case, which suits memoization
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, key: K, f: F) -> &mut V {
+        self.entry(key).or_insert_with(f)
+    }
+}
+
+impl<'a, K, V, C: Compare<K>> Entry<'a, K, V, C> {
+
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(e) => e.key(),
+            Entry::Vacant(e) => e.key()
+        }
+    }
+
+    // Inserts default if the key is absent, then returns the key's value
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(default())
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V where V: Default {
+        self.or_insert_with(V::default)
+    }
+
+    // Calls f on the value if the key is present, then hands the entry back
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut e) => {
+                f(e.get_mut());
+                Entry::Occupied(e)
+            },
+            Entry::Vacant(e) => Entry::Vacant(e)
+        }
+    }
+}
+
+impl<'a, K, V, C: Compare<K>> OccupiedEntry<'a, K, V, C> {
+
+    pub fn key(&self) -> &K {
+        self.tree.key(self.idx)
+    }
+
+    // Index of the key's node, which is the root
+    pub fn index(&self) -> NodeId {
+        self.idx
+    }
+
+    pub fn get(&self) -> &V {
+        self.tree.value(self.idx)
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        self.tree.value_mut(self.idx)
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        self.tree.value_mut(self.idx)
+    }
+
+    // Replaces the value, returning the old one
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    // Takes the key out of the tree, returning its value; the node is
+    // already the root, so no further search is needed
+    pub fn remove(self) -> V {
+        self.tree.remove_root().1
+    }
+}
+
+impl<'a, K, V, C: Compare<K>> VacantEntry<'a, K, V, C> {
+
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    // Inserts the key with value and returns the value. The search for key
+    // left its neighbor at the root, so the insert finds its place at once.
+    pub fn insert(self, value: V) -> &'a mut V {
+        let idx = self.tree.insert_pair(self.key, value);
+        self.tree.value_mut(idx)
+    }
+}
@@ -0,0 +1,335 @@
+/*
+* iter.rs
+*
+* Iterators over a SplayTree. Trees built by new() are long paths, so every
+* traversal here keeps its own stack on the heap instead of recursing.
+*/
+
+use std::collections::VecDeque;
+use std::ops::{Bound, RangeBounds};
+
+use crate::{Compare, Natural, Node, NodeId, SplayTree, LEFT, RIGHT};
+
+// Consuming iterator over (key, value) pairs in sorted order
+pub struct IntoIter<K, V> {
+    nodes: Vec<Node<K, V>>,
+    stack: Vec<NodeId>,     // ancestors whose entries have not been yielded yet
+    cur: Option<NodeId>,    // next subtree to descend into
+    remaining: usize
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        while let Some(i) = self.cur {
+            self.stack.push(i);
+            self.cur = self.nodes[i].children[LEFT];
+        }
+        let i = self.stack.pop()?;
+        self.cur = self.nodes[i].children[RIGHT];
+        self.remaining -= 1;
+        self.nodes[i].entry.take()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V, C> IntoIterator for SplayTree<K, V, C> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> IntoIter<K, V> {
+        IntoIter { nodes: self.nodes, stack: Vec::new(), cur: self.root, remaining: self.len }
+    }
+}
+
+// Consuming iterator over the keys in sorted order, see SplayTree::into_keys
+pub struct IntoKeys<K, V> {
+    inner: IntoIter<K, V>
+}
+
+impl<K, V> Iterator for IntoKeys<K, V> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<K> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+// Iterator over (key, value) references in sorted order, see SplayTree::iter
+pub struct Iter<'a, K, V> {
+    nodes: &'a Vec<Node<K, V>>,
+    stack: Vec<NodeId>,     // ancestors whose entries have not been yielded yet
+    cur: Option<NodeId>,    // next subtree to descend into
+    remaining: usize
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        let nodes = self.nodes;
+        while let Some(i) = self.cur {
+            self.stack.push(i);
+            self.cur = nodes[i].children[LEFT];
+        }
+        let i = self.stack.pop()?;
+        self.cur = nodes[i].children[RIGHT];
+        self.remaining -= 1;
+        Some((nodes[i].key(), nodes[i].value()))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+// Keys in sorted order, see SplayTree::keys
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<&'a K> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+// Values in the sorted order of their keys, see SplayTree::values
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<&'a V> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+// Mutable values in the sorted order of their keys, see SplayTree::values_mut
+pub struct ValuesMut<'a, K, V> {
+    inner: IterMut<'a, K, V>
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<&'a mut V> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+// Mutable iterator over (key, value) pairs in sorted order, see SplayTree::iter_mut
+pub struct IterMut<'a, K, V> {
+    slots: Vec<Option<(&'a K, &'a mut V)>>,  // every live entry, by arena index
+    order: std::vec::IntoIter<NodeId>          // arena indices in sorted key order
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        let i = self.order.next()?;
+        self.slots[i.index()].take()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.order.size_hint()
+    }
+}
+
+// Indices of the nodes in level order, see SplayTree::bfs
+pub struct Bfs<'a, K, V> {
+    nodes: &'a Vec<Node<K, V>>,
+    queue: VecDeque<NodeId>     // nodes seen but not yet yielded, shallowest first
+}
+
+impl<K, V> Iterator for Bfs<'_, K, V> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let i = self.queue.pop_front()?;
+        self.queue.extend(self.nodes[i].children.into_iter().flatten());
+        Some(i)
+    }
+}
+
+// Indices from a node up to the root, see SplayTree::parent_chain
+pub struct ParentChain<'a, K, V> {
+    nodes: &'a Vec<Node<K, V>>,
+    next: Option<NodeId>,
+    remaining: usize    // most nodes left to yield, so a loop of parent pointers still ends
+}
+
+impl<K, V> Iterator for ParentChain<'_, K, V> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let i = self.next.filter(|_| self.remaining > 0)?;
+        let node = self.nodes.get(i.0)?;
+        self.remaining -= 1;
+        self.next = node.parent;
+        Some(i)
+    }
+}
+
+// Indices of the nodes whose keys fall in a range, in sorted order, see SplayTree::range
+pub struct Range<'a, K, V, R, C = Natural> {
+    tree: &'a SplayTree<K, V, C>,
+    next: Option<NodeId>,
+    bounds: R
+}
+
+impl<K, V, R: RangeBounds<K>, C: Compare<K>> Iterator for Range<'_, K, V, R, C> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        let i = self.next?;
+        let key = self.tree.key(i);
+        let in_range = match self.bounds.end_bound() {
+            Bound::Included(hi) => self.tree.cmp.compare(key, hi).is_le(),
+            Bound::Excluded(hi) => self.tree.cmp.compare(key, hi).is_lt(),
+            Bound::Unbounded => true
+        };
+        if !in_range {
+            self.next = None;
+            return None;
+        }
+        self.next = self.tree.next_inorder(i);
+        Some(i)
+    }
+}
+
+impl<K, V, C: Compare<K>> SplayTree<K, V, C> {
+
+    /**
+     * Splays the first node in range to the root, then walks successors from
+     * there, yielding the index of each node whose key is in range. Accepts
+     * any range over keys: lo..hi, lo..=hi, lo.., ..hi, or .. for all of them.
+     *
+     * The iterator borrows the tree immutably, so nothing can splay it away
+     * from under the walk.
+     */
+    pub fn range<R: RangeBounds<K>>(&mut self, bounds: R) -> Range<'_, K, V, R, C> {
+        let next = match bounds.start_bound() {
+            Bound::Included(lo) => self.find(lo).or_else(|| self.successor(lo)),
+            Bound::Excluded(lo) => self.successor(lo),
+            Bound::Unbounded => self.min()
+        };
+        Range { tree: self, next, bounds }
+    }
+
+    /**
+     * Removes the keys in range from the tree and yields them in sorted
+     * order. The range is cut out with two splits and the pieces on either
+     * side joined back together, so the tree is whole again before the first
+     * key is yielded, and every node gets a new index.
+     */
+    pub fn drain_range<R: RangeBounds<K>>(&mut self, bounds: R) -> IntoKeys<K, V> where C: Clone {
+        let tree = std::mem::replace(self, self.empty_like());
+        let (left, rest) = match bounds.start_bound() {
+            Bound::Included(lo) => tree.split_at(lo, false),
+            Bound::Excluded(lo) => tree.split_at(lo, true),
+            Bound::Unbounded => (self.empty_like(), tree)
+        };
+        let (middle, right) = match bounds.end_bound() {
+            Bound::Included(hi) => rest.split_at(hi, true),
+            Bound::Excluded(hi) => rest.split_at(hi, false),
+            Bound::Unbounded => (rest, self.empty_like())
+        };
+        *self = SplayTree::join(left, right);
+        middle.into_keys()
+    }
+}
+
+impl<K, V, C> SplayTree<K, V, C> {
+
+    // Consumes the tree, yielding its keys in sorted order
+    pub fn into_keys(self) -> IntoKeys<K, V> {
+        IntoKeys { inner: self.into_iter() }
+    }
+
+    // Yields every key and value in sorted order, without splaying, from a
+    // walk with an explicit stack like into_iter's
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { nodes: &self.nodes, stack: Vec::new(), cur: self.root, remaining: self.len }
+    }
+
+    // Yields node indices level by level from the root, left to right within
+    // a level, without splaying. The queue never spans more than two levels,
+    // so a path such as new(n) needs only O(1) extra memory.
+    pub fn bfs(&self) -> Bfs<'_, K, V> {
+        Bfs { nodes: &self.nodes, queue: self.root.into_iter().collect() }
+    }
+
+    // Yields idx, then its parent, and so on up to the root, without
+    // splaying, so it yields depth(idx) + 1 indices. Like path_len_to_root it
+    // follows parent pointers at most len() times, so on a corrupt tree whose
+    // pointers loop it stops instead of running forever, and it stops before
+    // a parent index past the end of the arena. Panics if idx is out of range
+    // or has been removed, as a removed slot's parent link may be stale.
+    pub fn parent_chain(&self, idx: NodeId) -> ParentChain<'_, K, V> {
+        if let Err(e) = self.check_index(idx) {
+            panic!("parent_chain: {}", e);
+        }
+        ParentChain { nodes: &self.nodes, next: Some(idx), remaining: self.len }
+    }
+
+    // Index of the k-th node in bfs order (counting from 0), or None if the
+    // tree has k or fewer nodes
+    pub fn nth_from_root(&self, k: usize) -> Option<NodeId> {
+        self.bfs().nth(k)
+    }
+
+    // The keys in sorted order, without splaying
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    // The values in the sorted order of their keys, without splaying
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    // The values in the sorted order of their keys, to modify in place; see iter_mut
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut { inner: self.iter_mut() }
+    }
+
+    /**
+     * Yields every key with a mutable reference to its value, in sorted
+     * order, without splaying. The sorted order of indices comes from a walk
+     * with an explicit stack before the first pair is yielded; the references
+     * themselves are all split off the arena at once, one per slot, so each
+     * is handed out exactly once and no unsafe code is needed. Costs O(n)
+     * extra memory for the two.
+     */
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        let order = self.inorder_indices().into_iter();
+        let slots = self.nodes.iter_mut().map(|node| node.entry.as_mut().map(|(k, v)| (&*k, v))).collect();
+        IterMut { slots, order }
+    }
+}
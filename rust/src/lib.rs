@@ -0,0 +1,2658 @@
+/*
+* lib.rs
+*
+* Rust implementation of a splay tree over ordered keys for 15-451 (Algorithm Design and Analysis).
+*
+* For more information, consult the lecture notes:
+*
+* https://www.cs.cmu.edu/~15451-f22/lectures/lec04-splay.pdf
+*
+* Shiva Peri <shivanap@andrew.cmu.edu>
+* Last update: 09/18/22
+*/
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Index, IndexMut};
+
+mod aggregate;
+mod cache;
+mod compact;
+mod compare;
+mod entry;
+mod iter;
+mod rope;
+mod seq;
+#[cfg(feature = "serde")]
+mod serialize;
+
+pub use aggregate::{AggSplayTree, Bounded, MaxMonoid, MinMonoid, Monoid, SumMonoid, SumSplayTree};
+pub use cache::SplayCache;
+pub use compact::CompactSplayTree;
+pub use compare::{Compare, Natural};
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use iter::{Bfs, IntoIter, IntoKeys, Iter, IterMut, Keys, ParentChain, Range, Values, ValuesMut};
+pub use rope::SplayRope;
+pub use seq::SeqSplay;
+
+// Errors reported by the fallible tree operations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SplayError {
+    OutOfRange { index: NodeId, len: usize }, // node index past the end of the tree
+    Removed { index: NodeId },                // node index whose node has been removed
+    Cycle { index: NodeId }                   // node whose parent pointers loop without reaching the root
+}
+
+impl fmt::Display for SplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplayError::OutOfRange { index, len } =>
+                write!(f, "node index {} out of range for tree of {} nodes", index, len),
+            SplayError::Removed { index } =>
+                write!(f, "node index {} refers to a removed node", index),
+            SplayError::Cycle { index } =>
+                write!(f, "parent pointers from node {} form a cycle", index)
+        }
+    }
+}
+
+impl std::error::Error for SplayError {}
+
+// One step of a recorded workload for SplayTree::apply_ops. A Vec<Op> from a
+// fuzzer or a bug report replays the same way every time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Op<K> {
+    Splay(NodeId),  // splay the node at this index, if there is one
+    Find(K),
+    Insert(K),
+    Remove(K)
+}
+
+/**
+ * A handle to a node in a tree's arena, as returned by find, insert, and the
+ * other lookups, and taken by key, get, and splay. It is a distinct type
+ * from usize so that a handle cannot be passed where a key, rank, or count
+ * is expected, or the other way around:
+ *
+ * ```compile_fail
+ * let mut tree = splay_tree::SplayTree::new(8);
+ * let idx: usize = tree.find(&3).unwrap();
+ * ```
+ *
+ * A handle stays valid until its node is removed, as described at remove,
+ * and means nothing in any other tree.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct NodeId(usize);
+
+impl NodeId {
+
+    // The handle for position index in a tree's arena, e.g. from an earlier index()
+    pub fn from_index(index: usize) -> Self {
+        NodeId(index)
+    }
+
+    // Position of the node in the tree's arena
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/**
+ * A borrowed, read-only view of one node, from SplayTree::node. It exposes
+ * the node's key, value, and links without the node's layout, so code that
+ * walks the tree, such as a visualizer or an external validator, keeps
+ * working if the layout changes. The links are handles to pass back to node.
+ */
+pub struct NodeView<'a, K, V = ()> {
+    idx: NodeId,
+    node: &'a Node<K, V>
+}
+
+impl<'a, K, V> NodeView<'a, K, V> {
+
+    pub fn index(&self) -> NodeId {
+        self.idx
+    }
+
+    pub fn key(&self) -> &'a K {
+        self.node.key()
+    }
+
+    pub fn value(&self) -> &'a V {
+        self.node.value()
+    }
+
+    // None for the root
+    pub fn parent(&self) -> Option<NodeId> {
+        self.node.parent
+    }
+
+    pub fn left(&self) -> Option<NodeId> {
+        self.node.children[LEFT]
+    }
+
+    pub fn right(&self) -> Option<NodeId> {
+        self.node.children[RIGHT]
+    }
+
+    // Number of nodes in the subtree rooted here, this one included
+    pub fn size(&self) -> usize {
+        self.node.size
+    }
+}
+
+// Lets the tree index its arena by handle directly, as nodes[idx]
+impl<K, V> Index<NodeId> for Vec<Node<K, V>> {
+    type Output = Node<K, V>;
+
+    fn index(&self, idx: NodeId) -> &Node<K, V> {
+        &self[idx.0]
+    }
+}
+
+impl<K, V> IndexMut<NodeId> for Vec<Node<K, V>> {
+    fn index_mut(&mut self, idx: NodeId) -> &mut Node<K, V> {
+        &mut self[idx.0]
+    }
+}
+
+// Indices into Node::children. The two are mirror images: 1 - dir is the other side.
+const LEFT: usize = 0;
+const RIGHT: usize = 1;
+
+// The three cases of a bottom-up splay step for a node x with parent y and
+// grandparent z, each covering both mirror images through its sides; see
+// SplayTree::splay_step
+enum SplayStep {
+    Zig { y: NodeId, dir: usize },                              // y is the root, x on side dir
+    ZigZig { y: NodeId, z: NodeId, dir: usize },                // x and y both on side dir
+    ZigZag { y: NodeId, z: NodeId, x_dir: usize, y_dir: usize } // x and y on opposite sides
+}
+
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Node<K, V> {
+    entry: Option<(K, V)>,  // search key and its value, or None once the node is removed;
+                            // inorder traversal visits keys in sorted order, rotations never touch this
+    parent: Option<NodeId>, // None, or Some(Index) into Vec<Nodes>
+    children: [Option<NodeId>; 2],  // [LEFT] and [RIGHT]: None, or Some(Index) into Vec<Nodes>
+    size: usize,            // number of nodes in the subtree rooted here
+    accesses: u64           // times this node was splayed since the last reset_accesses
+}
+
+impl<K, V> Node<K, V> {
+
+    fn key(&self) -> &K {
+        &self.entry.as_ref().expect("node has been removed").0
+    }
+
+    fn value(&self) -> &V {
+        &self.entry.as_ref().expect("node has been removed").1
+    }
+
+    fn value_mut(&mut self) -> &mut V {
+        &mut self.entry.as_mut().expect("node has been removed").1
+    }
+}
+
+// The whole arena is owned by the tree, so clone is a deep copy costing O(n)
+// time and memory; the copy shares nothing and can be splayed independently.
+#[derive(Clone)]
+pub struct SplayTree<K, V = (), C = Natural> {
+    root: Option<NodeId>,   // None for an empty tree, or Some(Index) of root Node in SplayTree::nodes
+    nodes: Vec<Node<K, V>>, // vector of nodes
+    free: Vec<NodeId>,      // indices of removed nodes, reused by later inserts
+    len: usize,             // number of live nodes in the tree
+    rotations: u64,         // rotations done by splay since the last reset_counters
+    splay_steps: u64,       // zig, zig-zig, and zig-zag steps done by splay since the last reset_counters
+    cmp: C                  // the order of the keys; Natural for their own Ord
+}
+
+impl SplayTree<usize> {
+
+    /**
+     * Creates a splay tree of size n whose inorder traversal is
+     *
+     *  0, 1, 2, ..., n-1
+     *
+     * Each node's key is its own index. See from_sorted_keys for the shape.
+     */
+    pub fn new(n: usize) -> Self {
+        Self::from_sorted_keys((0..n).collect())
+    }
+
+    // Turns the tree back into new(n), reusing the node arena's allocation
+    pub fn reset_to_path(&mut self, n: usize) {
+        self.clear();
+        self.push_path((0..n).map(|i| (i, ())));
+    }
+}
+
+impl<K: Ord> SplayTree<K> {
+
+    // Creates a splay tree over the given strictly increasing keys with no
+    // values attached. See from_sorted_pairs for the shape.
+    pub fn from_sorted_keys(keys: Vec<K>) -> Self {
+        Self::from_sorted_pairs(keys.into_iter().map(|k| (k, ())).collect())
+    }
+}
+
+impl<K, C: Compare<K>> SplayTree<K, (), C> {
+
+    // Inserts key and returns its index. See insert_pair.
+    pub fn insert(&mut self, key: K) -> NodeId {
+        self.insert_pair(key, ())
+    }
+
+    // Removes and returns the key of the first node in inorder, which is the
+    // smallest key when the tree is ordered, or None if the tree is empty. The
+    // tree can serve as a priority queue with O(log n) amortized pops.
+    pub fn pop_min(&mut self) -> Option<K> {
+        self.min()?;
+        let (key, _) = self.remove_root();
+        Some(key)
+    }
+
+    // Removes and returns the key of the last node in inorder, which is the
+    // largest key when the tree is ordered, or None if the tree is empty
+    pub fn pop_max(&mut self) -> Option<K> {
+        self.max()?;
+        let (key, _) = self.remove_root();
+        Some(key)
+    }
+
+    /**
+     * Applies ops in order. A Splay of an index that is out of range or
+     * removed is skipped, so any sequence a fuzzer makes up is a valid input.
+     *
+     * In debug builds the invariants are checked after every op, and the
+     * first op to break them panics with its position in ops, so the
+     * sequence up to there is the repro.
+     */
+    pub fn apply_ops(&mut self, ops: &[Op<K>]) where K: Clone {
+        for (i, op) in ops.iter().enumerate() {
+            match op {
+                Op::Splay(idx) => { let _ = self.try_splay(*idx); },
+                Op::Find(key) => { self.find(key); },
+                Op::Insert(key) => { self.insert(key.clone()); },
+                Op::Remove(key) => { self.remove(key); }
+            }
+            if cfg!(debug_assertions) {
+                if let Err(e) = self.check_invariants() {
+                    panic!("apply_ops: op {} left the tree broken: {}", i, e);
+                }
+            }
+        }
+    }
+}
+
+impl<K, V> SplayTree<K, V> {
+
+    /**
+     * Creates a splay tree whose inorder traversal is the given (key, value)
+     * pairs, whose keys must be strictly increasing. The pair at position i is
+     * stored at index i.
+     *
+     * The nodes are arranged in a linked-list-like structure rooted at the
+     * largest key:
+     *
+     * ```text
+     *       keys[n-1]
+     *      /
+     *    ...
+     *    /
+     *   keys[0]
+     * ```
+     *
+     * No keys gives an empty tree with no root
+     */
+    pub fn from_sorted_pairs(pairs: Vec<(K, V)>) -> Self where K: Ord {
+        debug_assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0), "keys must be strictly increasing");
+
+        let mut tree = Self::empty(pairs.len(), Natural);
+        tree.push_path(pairs);
+        tree
+    }
+
+    // An empty tree whose node arena has room for cap nodes, so that up to
+    // cap inserts never reallocate it
+    pub fn with_capacity(cap: usize) -> Self {
+        Self::empty(cap, Natural)
+    }
+}
+
+impl<K, V, C: Compare<K>> SplayTree<K, V, C> {
+
+    // Walks down from the root looking for key. Returns the node the search
+    // stops at (the match, or the last node on the path) together with how key
+    // compares to that node's key, or None for an empty tree.
+    fn descend(&self, key: &K) -> Option<(NodeId, Ordering)> {
+        let mut cur = self.root?;
+        loop {
+            let node = &self.nodes[cur];
+            let ord = self.cmp.compare(key, node.key());
+            let next = match ord {
+                Ordering::Less => node.children[LEFT],
+                Ordering::Greater => node.children[RIGHT],
+                Ordering::Equal => None
+            };
+            match next {
+                None => return Some((cur, ord)),
+                Some(i) => cur = i
+            }
+        }
+    }
+
+    // Indices of the nodes a search for key visits, from the root down to the
+    // node find would splay; the tree is left as it is. Empty for an empty tree.
+    pub fn search_path(&self, key: &K) -> Vec<NodeId> {
+        let mut path = Vec::new();
+        let mut cur = self.root;
+        while let Some(i) = cur {
+            path.push(i);
+            let node = &self.nodes[i];
+            cur = match self.cmp.compare(key, node.key()) {
+                Ordering::Less => node.children[LEFT],
+                Ordering::Greater => node.children[RIGHT],
+                Ordering::Equal => None
+            };
+        }
+        path
+    }
+
+    /**
+     * Inserts key with the given value and returns the index of its node,
+     * which ends up at the root.
+     *
+     * The search for key splays the last node on its path to the root. If key
+     * is already present that node is the match: keys are never duplicated, so
+     * the existing node keeps its index and only its value is replaced.
+     * Otherwise the root is key's predecessor or successor, and the tree is
+     * split around it with the new node hung above both halves:
+     *
+     * ```text
+     *        r                    key                       key
+     *       / \      key < r     /   \       key > r       /   \
+     *      A   B     =======>   A     r      =======>     r     B
+     *                                  \                 /
+     *                                   B               A
+     * ```
+     */
+    pub fn insert_pair(&mut self, key: K, value: V) -> NodeId {
+
+        let r = match self.root {
+            None => {
+                let idx = self.alloc(key, value);
+                self.set_root(Some(idx));
+                return idx;
+            },
+            Some(_) => match self.find(&key) {
+                Some(idx) => {
+                    *self.nodes[idx].value_mut() = value;
+                    return idx;
+                },
+                None => self.root.unwrap()
+            }
+        };
+
+        // the new node takes r's subtree on the side key falls, and r on the other
+        let dir = if self.cmp.compare(&key, self.nodes[r].key()).is_lt() { LEFT } else { RIGHT };
+        let idx = self.alloc(key, value);
+        let a_idx = self.nodes[r].children[dir].take();
+        self.set_child(idx, dir, a_idx);
+        self.set_child(idx, 1 - dir, Some(r));
+        self.update_size(r);
+        self.update_size(idx);
+        self.set_root(Some(idx));
+        self.verify_bst_order();
+        idx
+    }
+
+    /**
+     * Removes key from the tree, returning whether it was present.
+     *
+     * key is splayed to the root, detached, and its two subtrees are joined
+     * back together. The freed index goes on the free list for a later
+     * insert to reuse; the indices of all other nodes stay valid.
+     */
+    pub fn remove(&mut self, key: &K) -> bool {
+        match self.find(key) {
+            None => false,
+            Some(_) => {
+                self.remove_root();
+                self.verify_bst_order();
+                true
+            }
+        }
+    }
+
+    /**
+     * Splits the tree into the keys <= key and the keys > key.
+     *
+     * The search for key splays the last node on its path, which is either
+     * the largest key <= key or the smallest key > key, so cutting one of
+     * the root's links separates the two halves. Each half is moved into its
+     * own arena, so node indices from this tree are not valid in either.
+     */
+    pub fn split(self, key: &K) -> (Self, Self) where C: Clone {
+        self.split_at(key, true)
+    }
+
+    /**
+     * Moves the keys >= key into a new tree and returns it, keeping the keys
+     * < key, like BTreeMap::split_off. Both trees get fresh arenas, as with
+     * split, so indices from before the call are not valid in either.
+     */
+    pub fn split_off(&mut self, key: &K) -> Self where C: Clone {
+        let tree = std::mem::replace(self, self.empty_like());
+        let (left, right) = tree.split_at(key, false);
+        *self = left;
+        right
+    }
+
+    // split, with key itself going to the left half if key_left and the right otherwise
+    fn split_at(mut self, key: &K, key_left: bool) -> (Self, Self) where C: Clone {
+        if self.root.is_none() {
+            return (self.empty_like(), self.empty_like());
+        }
+        self.find(key);
+
+        let r = self.root.unwrap();
+        let goes_left = match self.cmp.compare(self.nodes[r].key(), key) {
+            Ordering::Less => true,
+            Ordering::Equal => key_left,
+            Ordering::Greater => false
+        };
+        let (left, right) = if goes_left {
+            (Some(r), self.nodes[r].children[RIGHT].take())
+        }
+        else {
+            (self.nodes[r].children[LEFT].take(), Some(r))
+        };
+        self.update_size(r);
+        let (left, right) = (self.take_subtree(left), self.take_subtree(right));
+        left.verify_bst_order();
+        right.verify_bst_order();
+        (left, right)
+    }
+
+    /**
+     * Joins two trees into one, where every key in left must be strictly
+     * less than every key in right.
+     *
+     * right's nodes are appended to left's arena, so indices from left stay
+     * valid and indices from right are shifted up by left's arena size. The
+     * maximum of left is then splayed to the root and right hung off it.
+     */
+    pub fn join(mut left: Self, right: Self) -> Self {
+        debug_assert!(match (left.root, right.root) {
+            (Some(a), Some(b)) => left.cmp.compare(left.nodes[left.rightmost(a)].key(), right.nodes[right.leftmost(b)].key()).is_lt(),
+            _ => true
+        }, "every key in left must be less than every key in right");
+
+        left.hang_after(right);
+        left.verify_bst_order();
+        left
+    }
+
+    /**
+     * Adds a batch of pairs whose keys are strictly increasing and all
+     * greater than every key already in the tree. The batch is allocated and
+     * linked into a balanced subtree in O(m), then hung after the maximum as
+     * in join, for O(m + log n) amortized in place of m inserts that each
+     * search and splay. The order of the keys is only checked in debug builds.
+     */
+    pub fn merge_sorted_into<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        let mut batch = Vec::new();
+        for (key, value) in pairs {
+            debug_assert!(batch.last().copied().or_else(|| self.last()).is_none_or(|p| self.cmp.compare(self.nodes[p].key(), &key).is_lt()),
+                "merge_sorted_into: keys must increase and start above the tree's maximum");
+            batch.push(self.alloc(key, value));
+        }
+        if batch.is_empty() {
+            return;
+        }
+        let sub = self.build_balanced(&batch, None);
+        self.join_roots(self.root, sub);
+        self.verify_bst_order();
+    }
+
+    /**
+     * Moves every key of other into this tree, like BTreeMap::append. For a
+     * key in both, other's value wins, the same as inserting it.
+     *
+     * When one tree's keys all come before the other's, the two are joined in
+     * O(m) for the appended arena instead of O(m log n) for inserting key by
+     * key. Indices from this tree stay valid unless other's keys all come
+     * first, in which case this tree's nodes are shifted up as in join.
+     */
+    pub fn append(&mut self, other: Self) where C: Clone {
+        let tree = std::mem::replace(self, self.empty_like());
+        let before = |a: &Self, b: &Self| match (a.last(), b.first()) {
+            (Some(max), Some(min)) => a.cmp.compare(a.key(max), b.key(min)).is_lt(),
+            _ => true
+        };
+        *self = if before(&tree, &other) {
+            Self::join(tree, other)
+        }
+        else if before(&other, &tree) {
+            Self::join(other, tree)
+        }
+        else {
+            let mut tree = tree;
+            for (key, value) in other {
+                tree.insert_pair(key, value);
+            }
+            tree
+        };
+    }
+
+    /**
+     * The union of two trees, with each key in both appearing once. As in
+     * append, other's value wins for such a key.
+     *
+     * This is the split/join recursion for balanced trees: the smaller tree,
+     * rebalanced first, gives the pivots. Its root key splits the other tree,
+     * the two sides are unioned with the root's subtrees recursively, and the
+     * results are joined back around the root. For m keys in the smaller tree
+     * and n in the larger that is O(m log(n/m)) splits and joins. Here every
+     * split and join also moves nodes between arenas, which adds O(n + m) per
+     * level of the recursion, for O((n + m) log m) overall.
+     */
+    pub fn union(self, other: Self) -> Self where C: Clone {
+        let (mut pivots, rest, pivots_win) = if self.len <= other.len {
+            (self, other, false)
+        }
+        else {
+            (other, self, true)
+        };
+        pivots.rebalance();
+        Self::union_with(pivots, rest, pivots_win)
+    }
+
+    // union, taking the pivot's value for a shared key if pivots_win
+    fn union_with(mut pivots: Self, rest: Self, pivots_win: bool) -> Self where C: Clone {
+        let r = match pivots.root {
+            None => return rest,
+            Some(_) if rest.is_empty() => return pivots,
+            Some(r) => r
+        };
+        let [a_idx, b_idx] = std::mem::take(&mut pivots.nodes[r].children);
+        let left = pivots.take_subtree(a_idx);
+        let right = pivots.take_subtree(b_idx);
+        let (key, mut value) = pivots.nodes[r].entry.take().unwrap();
+
+        // split_at leaves a shared key at the root of below, with nothing to its right
+        let (mut below, above) = rest.split_at(&key, true);
+        if below.root.is_some_and(|b| below.cmp.compare(below.nodes[b].key(), &key).is_eq()) {
+            let (_, rest_value) = below.remove_root();
+            if !pivots_win {
+                value = rest_value;
+            }
+        }
+
+        let mut middle = pivots.empty_like();
+        middle.push_path([(key, value)]);
+        let left = Self::union_with(left, below, pivots_win);
+        let right = Self::union_with(right, above, pivots_win);
+        Self::join(Self::join(left, middle), right)
+    }
+
+    /**
+     * A new tree of the keys in both trees, with self's values. Both trees
+     * are walked in key order side by side, without splaying, in O(n + m),
+     * and the result is built balanced.
+     */
+    pub fn intersection(&self, other: &Self) -> Self where K: Clone, V: Clone, C: Clone {
+        self.filter_by(other, true)
+    }
+
+    // A new tree of the keys in self but not in other, built as intersection is
+    pub fn difference(&self, other: &Self) -> Self where K: Clone, V: Clone, C: Clone {
+        self.filter_by(other, false)
+    }
+
+    /**
+     * A new tree of the pairs f returns, called on each pair in key order;
+     * None drops the pair. As f may change keys, the result is rebuilt
+     * rather than reusing this tree's shape: the pairs are laid out directly
+     * if their keys still increase, and sorted first otherwise, with the
+     * last of any equal keys winning as if each pair were inserted in turn.
+     * The tree is then balanced as in rebalance. O(n log n) at worst.
+     */
+    pub fn filter_map_into<U, F: FnMut(K, V) -> Option<(K, U)>>(mut self, mut f: F) -> SplayTree<K, U, C> {
+        let mut pairs = Vec::with_capacity(self.len);
+        for i in self.inorder_indices() {
+            let (key, value) = self.nodes[i].entry.take().unwrap();
+            pairs.extend(f(key, value));
+        }
+
+        let cmp = self.cmp;
+        if !pairs.windows(2).all(|w| cmp.compare(&w[0].0, &w[1].0).is_lt()) {
+            // stable, so after reversing, the last of equal keys comes first and dedup keeps it
+            pairs.sort_by(|a, b| cmp.compare(&a.0, &b.0));
+            pairs.reverse();
+            pairs.dedup_by(|a, b| cmp.compare(&a.0, &b.0).is_eq());
+            pairs.reverse();
+        }
+        let mut tree = SplayTree::empty(pairs.len(), cmp);
+        tree.push_path(pairs);
+        tree.rebalance();
+        tree
+    }
+
+    // Copies self's pairs whose keys are in other if in_other, or not in other otherwise
+    fn filter_by(&self, other: &Self, in_other: bool) -> Self where K: Clone, V: Clone, C: Clone {
+        let theirs = other.inorder_indices();
+        let mut j = 0;
+        let mut pairs = Vec::new();
+        for i in self.inorder_indices() {
+            let key = self.nodes[i].key();
+            while j < theirs.len() && self.cmp.compare(other.nodes[theirs[j]].key(), key).is_lt() {
+                j += 1;
+            }
+            let found = j < theirs.len() && self.cmp.compare(other.nodes[theirs[j]].key(), key).is_eq();
+            if found == in_other {
+                pairs.push((key.clone(), self.nodes[i].value().clone()));
+            }
+        }
+        let mut tree = self.empty_like();
+        tree.push_path(pairs);
+        tree.rebalance();
+        tree
+    }
+
+    /**
+     * Searches for key and splays the node the search stops at, so the root
+     * afterwards is either the match or the last node on the search path.
+     * Splaying on a miss is what keeps the amortized cost O(log n).
+     *
+     * Returns the index of the node holding key, or None if it is absent.
+     */
+    pub fn find(&mut self, key: &K) -> Option<NodeId> {
+        let (idx, ord) = self.descend(key)?;
+        self.splay(Some(idx));
+        self.verify_bst_order();
+        match ord {
+            Ordering::Equal => Some(idx),
+            _ => None
+        }
+    }
+
+    // Splays the node for key, or on a miss the last node on its search
+    // path, the same as find; named for use alongside splay, which takes an index
+    pub fn splay_key(&mut self, key: &K) -> Option<NodeId> {
+        self.find(key)
+    }
+
+    /**
+     * Number of keys in the tree strictly less than key, which need not be
+     * present itself.
+     *
+     * After find splays the end of the search path, the root is key itself,
+     * its predecessor, or its successor, so only the root and its left
+     * subtree can be smaller than key.
+     */
+    pub fn rank(&mut self, key: &K) -> usize {
+        if self.root.is_none() {
+            return 0;
+        }
+        self.find(key);
+
+        let r = &self.nodes[self.root.unwrap()];
+        let below = self.subtree_size(r.children[LEFT]);
+        if self.cmp.compare(r.key(), key).is_lt() { below + 1 } else { below }
+    }
+
+    // Number of keys in [lo, hi), from two ranks instead of walking the range
+    pub fn count_range(&mut self, lo: &K, hi: &K) -> usize {
+        self.rank(hi).saturating_sub(self.rank(lo))
+    }
+
+    // Splays the node with the largest key strictly less than key, which need
+    // not be present itself, and returns its index, or None if there is none
+    pub fn predecessor(&mut self, key: &K) -> Option<NodeId> {
+        self.neighbor(key, LEFT, false)
+    }
+
+    // Splays the node with the smallest key strictly greater than key, which
+    // need not be present itself, and returns its index, or None if there is none
+    pub fn successor(&mut self, key: &K) -> Option<NodeId> {
+        self.neighbor(key, RIGHT, false)
+    }
+
+    // Splays the node with the smallest key >= key and returns its index, or
+    // None if there is none, as std::map::lower_bound does in C++
+    pub fn lower_bound(&mut self, key: &K) -> Option<NodeId> {
+        self.neighbor(key, RIGHT, true)
+    }
+
+    // Splays the node with the smallest key > key and returns its index, or
+    // None if there is none; the same node as successor
+    pub fn upper_bound(&mut self, key: &K) -> Option<NodeId> {
+        self.neighbor(key, RIGHT, false)
+    }
+
+    // Splays the node with the largest key <= key and returns its index, or
+    // None if there is none, as TreeMap::floorKey does in Java. Unlike
+    // predecessor, an exact match is its own floor.
+    pub fn floor(&mut self, key: &K) -> Option<NodeId> {
+        self.neighbor(key, LEFT, true)
+    }
+
+    // Splays the node with the smallest key >= key and returns its index, or
+    // None if there is none; the same node as lower_bound. Unlike successor,
+    // an exact match is its own ceiling.
+    pub fn ceiling(&mut self, key: &K) -> Option<NodeId> {
+        self.neighbor(key, RIGHT, true)
+    }
+
+    // As with rank, after find the root is key, its predecessor, or its
+    // successor; otherwise the neighbor on side dir is the nearest node in
+    // the root's subtree on that side. If inclusive, key itself counts.
+    fn neighbor(&mut self, key: &K, dir: usize, inclusive: bool) -> Option<NodeId> {
+        self.root?;
+        self.find(key);
+
+        let r = self.root.unwrap();
+        let ord = self.cmp.compare(self.nodes[r].key(), key);
+        let beyond = if dir == LEFT { ord == Ordering::Less } else { ord == Ordering::Greater };
+        if beyond || (inclusive && ord == Ordering::Equal) {
+            return Some(r);
+        }
+        let sub = self.nodes[r].children[dir]?;
+        let idx = if dir == LEFT { self.rightmost(sub) } else { self.leftmost(sub) };
+        Some(self.splay(Some(idx)))
+    }
+
+    /**
+     * Splays the node at idx to the root in a single pass down from the root,
+     * instead of climbing back up through parent pointers like splay does.
+     *
+     * Nodes smaller than idx's key are collected into a left tree and larger
+     * ones into a right tree as the search descends (a zig-zig rotates first,
+     * then links). When idx is reached, its subtrees are hung on the inner
+     * edges of the two trees, which become its children:
+     *
+     * ```text
+     *        L      x      R                     x
+     *         \    / \    /        ====>       /   \
+     *          l  A   B  r                    L     R
+     *                                          \   /
+     *                                           l r
+     *                                           \ /
+     *                                           A B
+     * ```
+     *
+     * This pairs up rotations from the top of the path rather than the
+     * bottom, so for nodes deeper than two the final shape can differ from
+     * splay's, though it has the same amortized O(log n) bound. The inorder
+     * sequence, sizes, and new root are always the same.
+     *
+     * Returns idx, now the root. Panics if idx is not a live node.
+     */
+    pub fn splay_top_down(&mut self, idx: NodeId) -> NodeId {
+        assert!(idx.0 < self.nodes.len() && self.nodes[idx].entry.is_some(), "splay_top_down: {} is not a node", idx);
+
+        let mut t = self.root.unwrap();
+        let mut left_spine = Vec::new();    // left tree, each node the right child of the one before
+        let mut right_spine = Vec::new();   // right tree, each node the left child of the one before
+
+        while t != idx {
+            let step_left = self.cmp.compare(self.nodes[idx].key(), self.nodes[t].key()).is_lt();
+            let dir = if step_left { LEFT } else { RIGHT };
+            let mut y = self.nodes[t].children[dir].unwrap();
+            let zig_zig = y != idx && self.cmp.compare(self.nodes[idx].key(), self.nodes[y].key()).is_lt() == step_left;
+
+            if zig_zig {
+                // rotate y above t; t's remaining subtrees are off the path, so its size is final
+                let b = self.nodes[y].children[1 - dir];
+                self.set_child(t, dir, b);
+                self.set_child(y, 1 - dir, Some(t));
+                self.update_size(t);
+                t = y;
+                y = self.nodes[t].children[dir].unwrap();
+            }
+
+            // link t into the right tree (when stepping left) or the left tree
+            if step_left {
+                if let Some(&r) = right_spine.last() {
+                    self.set_child(r, LEFT, Some(t));
+                }
+                right_spine.push(t);
+            }
+            else {
+                if let Some(&l) = left_spine.last() {
+                    self.set_child(l, RIGHT, Some(t));
+                }
+                left_spine.push(t);
+            }
+            t = y;
+        }
+
+        // reassemble: x's subtrees go on the inner edges, the two trees become its children
+        let a_idx = self.nodes[idx].children[LEFT];
+        let b_idx = self.nodes[idx].children[RIGHT];
+        match left_spine.last() {
+            Some(&l) => self.set_child(l, RIGHT, a_idx),
+            None => self.set_child(idx, LEFT, a_idx)
+        }
+        match right_spine.last() {
+            Some(&r) => self.set_child(r, LEFT, b_idx),
+            None => self.set_child(idx, RIGHT, b_idx)
+        }
+        for &i in left_spine.iter().rev().chain(right_spine.iter().rev()) {
+            self.update_size(i);
+        }
+        if let Some(&l) = left_spine.first() {
+            self.set_child(idx, LEFT, Some(l));
+        }
+        if let Some(&r) = right_spine.first() {
+            self.set_child(idx, RIGHT, Some(r));
+        }
+        self.update_size(idx);
+        self.set_root(Some(idx));
+        self.verify_bst_order();
+        idx
+    }
+
+    // Membership test built on find. It takes &mut self because it splays just
+    // like find does, so repeated lookups of the same key stay cheap.
+    pub fn contains(&mut self, key: &K) -> bool {
+        self.find(key).is_some()
+    }
+
+    /**
+     * The value for key, or None if it is absent, from a read-only search
+     * that works through a shared reference and leaves the tree as it is.
+     * Nothing is splayed, so unlike find there is no amortized bound: a key
+     * deep in the tree costs its full depth on every lookup, where find
+     * would have brought it to the root after the first.
+     */
+    pub fn get(&self, key: &K) -> Option<&V> {
+        match self.descend(key)? {
+            (idx, Ordering::Equal) => Some(self.nodes[idx].value()),
+            _ => None
+        }
+    }
+
+    // The value for key to modify in place, without splaying, as with get
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        match self.descend(key)? {
+            (idx, Ordering::Equal) => Some(self.nodes[idx].value_mut()),
+            _ => None
+        }
+    }
+
+    /**
+     * Checks the structure of the tree, for use in tests and fuzzing:
+     *
+     *  - the root has no parent, and every other reachable node's parent
+     *    lists it as a child
+     *  - every child index is in range and refers to a live node
+     *  - exactly len() nodes are reachable from the root, with no cycles
+     *  - every slot that is not reachable is removed and on the free list
+     *  - keys increase strictly along the inorder traversal
+     *  - every reachable node's size is the number of nodes in its subtree
+     *
+     * The error names the index of the first offending node found.
+     */
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let n = self.nodes.len();
+        let root = match self.root {
+            None if self.len == 0 => return self.check_free_list(&vec![false; n]),
+            None => return Err(format!("no root but len is {}", self.len)),
+            Some(r) => r
+        };
+        if root.0 >= n || self.nodes[root].entry.is_none() {
+            return Err(format!("root {} is not a live node", root));
+        }
+        if let Some(p) = self.nodes[root].parent {
+            return Err(format!("root {} has parent {}", root, p));
+        }
+
+        // inorder walk, checking each link as it is followed
+        let mut seen = vec![false; n];
+        let mut count = 0;
+        let mut prev: Option<NodeId> = None;
+        let mut stack = Vec::new();
+        let mut cur = Some(root);
+        while cur.is_some() || !stack.is_empty() {
+            while let Some(i) = cur {
+                if seen[i.0] {
+                    return Err(format!("node {} is reachable twice", i));
+                }
+                seen[i.0] = true;
+                count += 1;
+                if count > self.len {
+                    return Err(format!("more than len() = {} nodes reachable from the root", self.len));
+                }
+                stack.push(i);
+                cur = self.check_child(i, self.nodes[i].children[LEFT], "left")?;
+            }
+            let i = stack.pop().unwrap();
+            if let Some(p) = prev {
+                if !self.cmp.compare(self.nodes[p].key(), self.nodes[i].key()).is_lt() {
+                    return Err(format!("key of node {} is not greater than its predecessor {}", i, p));
+                }
+            }
+            prev = Some(i);
+            cur = self.check_child(i, self.nodes[i].children[RIGHT], "right")?;
+        }
+
+        if count != self.len {
+            return Err(format!("{} nodes reachable from the root but len() is {}", count, self.len));
+        }
+        if let Some((i, count)) = self.first_bad_size() {
+            return Err(format!("node {} has size {} but its subtree has {} nodes", i, self.nodes[i].size, count));
+        }
+        self.check_free_list(&seen)
+    }
+
+    // Whether every reachable node's size is the true number of nodes in its
+    // subtree; the size part of check_invariants, which also names the node
+    pub fn validate_sizes(&self) -> bool {
+        self.first_bad_size().is_none()
+    }
+
+    // Recounts every subtree bottom-up and returns the first node, in
+    // postorder, whose stored size is not its true count, with that count.
+    // Comparing against counts rather than the children's stored sizes
+    // blames a bad size on its own node and not on its parent as well. A
+    // node reached twice is reported too, with count 0.
+    fn first_bad_size(&self) -> Option<(NodeId, usize)> {
+        let mut count = vec![0; self.nodes.len()];
+        let mut seen = vec![false; self.nodes.len()];
+        // (node, children already counted)
+        let mut stack: Vec<(NodeId, bool)> = self.root.map(|r| (r, false)).into_iter().collect();
+        while let Some((i, expanded)) = stack.pop() {
+            let [l, r] = self.nodes[i].children;
+            if !expanded {
+                if seen[i.0] {
+                    return Some((i, 0));
+                }
+                seen[i.0] = true;
+                stack.push((i, true));
+                stack.extend(r.map(|r| (r, false)));
+                stack.extend(l.map(|l| (l, false)));
+                continue;
+            }
+            count[i.0] = 1 + l.map_or(0, |l| count[l.0]) + r.map_or(0, |r| count[r.0]);
+            if self.nodes[i].size != count[i.0] {
+                return Some((i, count[i.0]));
+            }
+        }
+        None
+    }
+
+    // Validates the link from node i to its child, returning the child
+    fn check_child(&self, i: NodeId, child: Option<NodeId>, side: &str) -> Result<Option<NodeId>, String> {
+        let c = match child {
+            None => return Ok(None),
+            Some(c) => c
+        };
+        if c.0 >= self.nodes.len() {
+            return Err(format!("node {} has out of range {} child {}", i, side, c));
+        }
+        if self.nodes[c].entry.is_none() {
+            return Err(format!("node {} has removed {} child {}", i, side, c));
+        }
+        if self.nodes[c].parent != Some(i) {
+            return Err(format!("node {} is the {} child of {} but has parent {:?}", c, side, i, self.nodes[c].parent.map(NodeId::index)));
+        }
+        Ok(Some(c))
+    }
+
+    // Every slot not reachable from the root must be removed and free, exactly once
+    fn check_free_list(&self, reachable: &[bool]) -> Result<(), String> {
+        let mut free = vec![false; self.nodes.len()];
+        for &i in &self.free {
+            if i.0 >= self.nodes.len() || free[i.0] || reachable[i.0] || self.nodes[i].entry.is_some() {
+                return Err(format!("free list entry {} is not a unique removed node", i));
+            }
+            free[i.0] = true;
+        }
+        match (0..self.nodes.len()).find(|&i| !reachable[i] && !free[i]) {
+            Some(i) => Err(format!("node {} is neither reachable nor free", i)),
+            None => Ok(())
+        }
+    }
+
+    // The key-order part of check_invariants, run at the end of the keyed
+    // operations (find, insert, remove, split, join, splay_top_down, and
+    // merge_sorted_into) so that one that misplaces a node panics itself
+    // rather than leaving a later search to go the wrong way. splay, pop_min,
+    // pop_max, and the other positional operations skip it, as they never
+    // compare keys and must keep working on a tree concat left out of order.
+    // Costs O(n) per call, so it only runs in this crate's own debug test
+    // builds and compiles away everywhere else.
+    #[cfg(all(test, debug_assertions))]
+    fn verify_bst_order(&self) {
+        let order = self.inorder_indices();
+        for w in order.windows(2) {
+            if !self.cmp.compare(self.nodes[w[0]].key(), self.nodes[w[1]].key()).is_lt() {
+                panic!("verify_bst_order: key of node {} is not greater than its predecessor {}", w[1], w[0]);
+            }
+        }
+    }
+
+    #[cfg(not(all(test, debug_assertions)))]
+    #[inline(always)]
+    fn verify_bst_order(&self) {}
+}
+
+impl<K, V, C> SplayTree<K, V, C> {
+
+    // An empty tree ordered by cmp, with room for cap nodes
+    fn empty(cap: usize, cmp: C) -> Self {
+        Self { root: None, nodes: Vec::with_capacity(cap), free: Vec::new(), len: 0, rotations: 0, splay_steps: 0, cmp }
+    }
+
+    // An empty tree in the same order as this one, with nothing allocated
+    fn empty_like(&self) -> Self where C: Clone {
+        Self::empty(0, self.cmp.clone())
+    }
+
+    // Number of nodes in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Removes every node, keeping the node arena's allocation, and resets the
+    // counters, leaving the same state as a newly built empty tree
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.nodes.clear();
+        self.free.clear();
+        self.len = 0;
+        self.reset_counters();
+    }
+
+    // Number of nodes the arena can hold before it has to reallocate
+    pub fn capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    // Makes room in the arena for at least additional more nodes, so that
+    // many inserts in a row never reallocate it. Slots on the free list are
+    // not counted, so this may reserve more than those inserts need.
+    pub fn reserve(&mut self, additional: usize) {
+        self.nodes.reserve(additional);
+    }
+
+    // Stacks pairs, in increasing key order, onto an empty tree as the left
+    // path described at from_sorted_pairs
+    fn push_path(&mut self, pairs: impl IntoIterator<Item = (K, V)>) {
+        debug_assert!(self.nodes.is_empty());
+        for (key, value) in pairs {
+            let i = NodeId(self.nodes.len());
+            let below = self.root;
+            self.nodes.push(Node{ entry: Some((key, value)), parent: None, children: [below, None], size: i.0 + 1, accesses: 0 });
+            if let Some(b) = below {
+                self.nodes[b].parent = Some(i);
+            }
+            self.root = Some(i);
+            self.len += 1;
+        }
+    }
+
+    // Key stored at the node at idx
+    pub fn key(&self, idx: NodeId) -> &K {
+        self.nodes[idx].key()
+    }
+
+    // Value stored at the node at idx; get looks a value up by key instead
+    pub fn value(&self, idx: NodeId) -> &V {
+        self.nodes[idx].value()
+    }
+
+    pub fn value_mut(&mut self, idx: NodeId) -> &mut V {
+        self.nodes[idx].value_mut()
+    }
+
+    // Swaps the values at a and b, leaving the keys, and so the shape, as they
+    // are. Reports either index that is not in the tree, and changes nothing then.
+    pub fn swap_values(&mut self, a: NodeId, b: NodeId) -> Result<(), SplayError> {
+        self.check_index(a)?;
+        self.check_index(b)?;
+        if a == b {
+            return Ok(());
+        }
+        let (lo, hi) = if a < b { (a, b) } else { (b, a) };
+        let (below, above) = self.nodes.split_at_mut(hi.0);
+        std::mem::swap(below[lo.0].value_mut(), above[0].value_mut());
+        Ok(())
+    }
+
+    // Whether idx refers to a live node, i.e. is in range and not removed.
+    // A removed node's slot is reused by a later insert, after which an old
+    // handle to it is live again but for the new key.
+    pub fn contains_index(&self, idx: NodeId) -> bool {
+        self.check_index(idx).is_ok()
+    }
+
+    // Ok if idx is a live node, or why not
+    fn check_index(&self, idx: NodeId) -> Result<(), SplayError> {
+        if idx.0 >= self.nodes.len() {
+            return Err(SplayError::OutOfRange { index: idx, len: self.nodes.len() });
+        }
+        if self.nodes[idx].entry.is_none() {
+            return Err(SplayError::Removed { index: idx });
+        }
+        Ok(())
+    }
+
+    // A read-only view of the node at idx and its links, for code that walks
+    // the tree's shape. Panics, as key does, if the node has been removed.
+    pub fn node(&self, idx: NodeId) -> NodeView<'_, K, V> {
+        let node = &self.nodes[idx];
+        assert!(node.entry.is_some(), "node: {} has been removed", idx);
+        NodeView { idx, node }
+    }
+
+    // Bytes allocated for the node arena
+    pub fn memory_bytes(&self) -> usize {
+        self.nodes.capacity() * std::mem::size_of::<Node<K, V>>()
+    }
+
+    // for debugging
+    pub fn print(&self) where K: fmt::Debug, V: fmt::Debug {
+        println!("\n{:?}", self);
+    }
+
+    // Detach the root node, join its subtrees in its place, and return its key and value
+    fn remove_root(&mut self) -> (K, V) {
+        let x_idx = self.root.expect("remove_root on an empty tree");
+        let x = &mut self.nodes[x_idx];
+        let a_idx = x.children[LEFT].take();
+        let b_idx = x.children[RIGHT].take();
+        let entry = x.entry.take().unwrap();
+        self.free.push(x_idx);
+        self.len -= 1;
+
+        self.join_roots(a_idx, b_idx);
+        entry
+    }
+
+    /**
+     * Makes a the whole tree with b hung off it, where a and b are detached
+     * subtrees and every key under a is smaller than every key under b.
+     *
+     * The maximum of a is splayed to a's root, which leaves it with no right
+     * child, and b becomes that right child:
+     *
+     * ```text
+     *       a                  max
+     *      / \      b         /   \
+     *    ...  max   ====>   ...    b
+     * ```
+     */
+    fn join_roots(&mut self, a_idx: Option<NodeId>, b_idx: Option<NodeId>) {
+        let a = match a_idx {
+            None => {
+                self.root = None;
+                if let Some(b) = b_idx {
+                    self.set_root(Some(b));
+                }
+                return;
+            },
+            Some(a) => a
+        };
+
+        self.set_root(Some(a));
+        let max = self.rightmost(a);
+        self.splay(Some(max));
+        self.set_child(max, RIGHT, b_idx);
+        self.update_size(max);
+    }
+
+    // Moves right's nodes onto the end of this tree's arena and joins its
+    // root after this tree's maximum, for join and concat
+    fn hang_after(&mut self, right: Self) {
+        let offset = self.nodes.len();
+        let shift = |i: Option<NodeId>| i.map(|i| NodeId(i.0 + offset));
+
+        self.nodes.extend(right.nodes.into_iter().map(|node| Node{
+            entry: node.entry,
+            parent: shift(node.parent),
+            children: node.children.map(shift),
+            size: node.size,
+            accesses: node.accesses
+        }));
+        self.free.extend(right.free.iter().map(|i| NodeId(i.0 + offset)));
+        self.len += right.len;
+        self.rotations += right.rotations;
+        self.splay_steps += right.splay_steps;
+
+        self.join_roots(self.root, shift(right.root));
+    }
+
+    // Moves the subtree rooted at sub into a new tree with its own arena,
+    // keeping its shape. The moved nodes are left behind as removed.
+    fn take_subtree(&mut self, sub: Option<NodeId>) -> Self where C: Clone {
+        let mut tree = self.empty_like();
+        let sub = match sub {
+            None => return tree,
+            Some(i) => i
+        };
+
+        // (old index, new parent index, side of the parent it hangs on) in preorder
+        let mut stack = vec![(sub, None, LEFT)];
+        while let Some((old, parent, side)) = stack.pop() {
+            let node = &mut self.nodes[old];
+            let new = NodeId(tree.nodes.len());
+            tree.nodes.push(Node{ entry: node.entry.take(), parent, children: [None, None], size: node.size, accesses: node.accesses });
+            self.len -= 1;
+
+            match parent {
+                None => tree.root = Some(new),
+                Some(p) => tree.nodes[p].children[side] = Some(new)
+            }
+            for dir in [RIGHT, LEFT] {
+                if let Some(c) = node.children[dir] {
+                    stack.push((c, Some(new), dir));
+                }
+            }
+        }
+        tree.len = tree.nodes.len();
+        tree
+    }
+
+    // Index of the smallest key in the subtree rooted at idx
+    fn leftmost(&self, mut idx: NodeId) -> NodeId {
+        while let Some(l) = self.nodes[idx].children[LEFT] {
+            idx = l;
+        }
+        idx
+    }
+
+    // Index of the largest key in the subtree rooted at idx
+    fn rightmost(&self, mut idx: NodeId) -> NodeId {
+        while let Some(r) = self.nodes[idx].children[RIGHT] {
+            idx = r;
+        }
+        idx
+    }
+
+    // Index of the node following idx in inorder, or None if idx holds the largest key
+    fn next_inorder(&self, idx: NodeId) -> Option<NodeId> {
+        if let Some(r) = self.nodes[idx].children[RIGHT] {
+            return Some(self.leftmost(r));
+        }
+        // climb until coming up from a left child
+        let mut cur = idx;
+        while let Some(p) = self.nodes[cur].parent {
+            if self.nodes[p].children[LEFT] == Some(cur) {
+                return Some(p);
+            }
+            cur = p;
+        }
+        None
+    }
+
+    // Place a detached node in a free slot, or at the end of the arena, and return its index
+    fn alloc(&mut self, key: K, value: V) -> NodeId {
+        let node = Node{ entry: Some((key, value)), parent: None, children: [None, None], size: 1, accesses: 0 };
+        self.len += 1;
+        match self.free.pop() {
+            Some(i) => {
+                self.nodes[i] = node;
+                i
+            },
+            None => {
+                self.nodes.push(node);
+                NodeId(self.nodes.len() - 1)
+            }
+        }
+    }
+
+    // Number of nodes in the subtree rooted at idx, 0 for an empty subtree
+    fn subtree_size(&self, idx: Option<NodeId>) -> usize {
+        match idx {
+            None => 0,
+            Some(i) => self.nodes[i].size
+        }
+    }
+
+    // Recompute the size of the node at node_idx from its children's sizes
+    fn update_size(&mut self, node_idx: NodeId) {
+        let node = &self.nodes[node_idx];
+        let size = 1 + self.subtree_size(node.children[LEFT]) + self.subtree_size(node.children[RIGHT]);
+        self.nodes[node_idx].size = size;
+    }
+
+    // Set the parent of the node at node_idx to the given node
+    fn set_parent(&mut self, node_idx: NodeId, parent_idx: Option<NodeId>) {
+        assert!(node_idx.0 < self.nodes.len());
+        let node = &mut self.nodes[node_idx];
+        node.parent = parent_idx;
+    }
+
+    // Set the child on side dir of the node at node_idx to the given node
+    fn set_child(&mut self, node_idx: NodeId, dir: usize, child_idx: Option<NodeId>) {
+
+        assert!(node_idx.0 < self.nodes.len());
+        let node = &mut self.nodes[node_idx];
+        node.children[dir] = child_idx;
+
+        if let Some(i) = child_idx {
+            self.set_parent(i, Some(node_idx));
+        }
+    }
+
+    // Which side of its parent the node at node_idx hangs on
+    fn side(&self, node_idx: NodeId) -> usize {
+        let parent = &self.nodes[self.nodes[node_idx].parent.unwrap()];
+        assert!(parent.children.contains(&Some(node_idx)));
+        if parent.children[LEFT] == Some(node_idx) { LEFT } else { RIGHT }
+    }
+
+    // Replace whichever child is currently equal to old with new_child
+    fn replace_child(&mut self, node_idx: NodeId, old_child: Option<NodeId>, new_child: Option<NodeId>) {
+
+        assert!(old_child.is_some());
+        assert!(new_child.is_some());
+        assert!(node_idx.0 < self.nodes.len());
+        let node = &mut self.nodes[node_idx];
+
+        assert!(node.children.contains(&old_child));
+        let dir = if node.children[LEFT] == old_child { LEFT } else { RIGHT };
+        node.children[dir] = new_child;
+        self.set_parent(new_child.unwrap(), Some(node_idx));
+    }
+
+    /**
+     * Lifts y's child on side dir above y. With dir = LEFT this is a right
+     * rotation about y; dir = RIGHT is the mirror image, a left rotation:
+     *
+     * ```text
+     *         z                                        z
+     *        /       rotate(y, LEFT)                  /
+     *       y      ===========================>      x
+     *      / \                                      / \
+     *     x   C                                    A   y
+     *    / \      <===========================       / \
+     *   A   B        rotate(x, RIGHT)                B   C
+     * ```
+     */
+    fn rotate(&mut self, y_idx: NodeId, dir: usize) {
+
+        let y = &self.nodes[y_idx];
+        let (name, side) = if dir == LEFT { ("rotate_right", "left") } else { ("rotate_left", "right") };
+        // x will replace y's position, so it cannot be null
+        let x_idx = y.children[dir].unwrap_or_else(|| panic!("{} on {}: missing {} child", name, y_idx, side));
+        let x = &self.nodes[x_idx];
+
+        let z_idx = y.parent;
+        let b_idx = x.children[1 - dir];        // the inner subtree changes sides
+        self.rotations += 1;
+
+        self.set_child(y_idx, dir, b_idx);          // y->set_left(B);
+        self.set_child(x_idx, 1 - dir, Some(y_idx));    // x->set_right(y);
+        self.update_size(y_idx);                    // y is now below x, so update it first
+        self.update_size(x_idx);
+
+        match z_idx {
+            None => self.set_root(Some(x_idx)),     // y is the root
+            Some(i) => self.replace_child(i, Some(y_idx), Some(x_idx))
+        }
+    }
+
+    /**
+     * One step of bottom-up splaying, moving x up one or two levels. The
+     * cases are shown with x on the left; each also has a mirror image.
+     *
+     * ```text
+     *                                  y             x
+     *   Zig (y is the tree root):     /     ====>     \
+     *                                x                 y
+     *
+     *                    z                         x
+     *                   /            y              \
+     *   Zig-zig:       y     ====>  / \   ====>      y
+     *                 /            x   z              \
+     *                x                                 z
+     *
+     *                    z              z
+     *                   /              /             x
+     *   Zig-zag:       y     ====>    x   ====>     / \
+     *                   \            /             y   z
+     *                    x          y
+     * ```
+     */
+    fn splay_step(&mut self, x_idx: Option<NodeId>) {
+        assert!(x_idx.is_some());
+        let step = match self.step_for(x_idx.unwrap()) {
+            None => return,     // root case, do nothing
+            Some(step) => step
+        };
+        self.splay_steps += 1;
+
+        match step {
+            SplayStep::Zig { y, dir } => self.rotate(y, dir),
+            SplayStep::ZigZig { y, z, dir } => {
+                self.rotate(z, dir);
+                self.rotate(y, dir);        // at the start of this stage, y is at the top
+            },
+            SplayStep::ZigZag { y, z, x_dir, y_dir } => {
+                self.rotate(y, x_dir);
+                self.rotate(z, y_dir);
+            }
+        }
+    }
+
+    // Which splay step x takes, with the indices and sides it needs read
+    // once up front; None if x is the root
+    fn step_for(&self, x_idx: NodeId) -> Option<SplayStep> {
+        let y = self.nodes[x_idx].parent?;
+        let x_dir = self.side(x_idx);
+        let step = match self.nodes[y].parent {
+            None => SplayStep::Zig { y, dir: x_dir },
+            Some(z) => match self.side(y) {
+                y_dir if y_dir == x_dir => SplayStep::ZigZig { y, z, dir: x_dir },
+                y_dir => SplayStep::ZigZag { y, z, x_dir, y_dir }
+            }
+        };
+        Some(step)
+    }
+
+    // Rotations done by splay, including the splays inside find, insert and
+    // the rest, since the tree was built or reset_counters was last called.
+    // splay_top_down is not counted.
+    pub fn rotation_count(&self) -> u64 {
+        self.rotations
+    }
+
+    // Zig, zig-zig, and zig-zag steps done by splay, counted like rotation_count
+    pub fn splay_step_count(&self) -> u64 {
+        self.splay_steps
+    }
+
+    pub fn reset_counters(&mut self) {
+        self.rotations = 0;
+        self.splay_steps = 0;
+    }
+
+    // Index of the live node splayed most often since the last reset_accesses,
+    // counting the splays inside find, insert and the rest. Ties go to the
+    // lowest index; None if the tree is empty.
+    pub fn most_accessed(&self) -> Option<NodeId> {
+        let live = (0..self.nodes.len()).map(NodeId).filter(|&i| self.nodes[i].entry.is_some());
+        live.min_by_key(|&i| std::cmp::Reverse(self.nodes[i].accesses))
+    }
+
+    pub fn reset_accesses(&mut self) {
+        for node in &mut self.nodes {
+            node.accesses = 0;
+        }
+    }
+
+    // The potential from the amortized analysis of splaying: the sum over all
+    // nodes of log2 of the size of their subtree
+    pub fn potential(&self) -> f64 {
+        self.nodes.iter().filter(|n| n.entry.is_some()).map(|n| (n.size as f64).log2()).sum()
+    }
+
+    /**
+     * The working-set bound on the rotations needed to access keys in the
+     * order given, starting from any tree of len() nodes:
+     *
+     *   sum over accesses of (3 (1 + 2 log2 k) + 1)  +  n (1 + 2 log2 n)
+     *
+     * where k is the number of distinct keys accessed since the previous
+     * access to the same key, itself included, or n for a first access. It
+     * is the access lemma with weight 1/k^2 on the key of working-set rank
+     * k; the weights add up to less than 2, which gives the 1 terms, and the
+     * last term bounds the drop in potential over the whole sequence.
+     *
+     * Ranks come from a move-to-front list, so this takes O(m d) for m
+     * accesses to d distinct keys.
+     */
+    pub fn working_set_bound(&self, accesses: &[K]) -> f64 where K: PartialEq {
+        let n = self.len.max(1) as f64;
+        let mut recent: Vec<&K> = Vec::new();
+        let mut bound = n * (1.0 + 2.0 * n.log2());
+        for key in accesses {
+            let k = match recent.iter().position(|&r| r == key) {
+                Some(pos) => {
+                    recent.remove(pos);
+                    (pos + 1) as f64
+                },
+                None => n
+            };
+            recent.insert(0, key);
+            bound += 3.0 * (1.0 + 2.0 * k.log2()) + 1.0;
+        }
+        bound
+    }
+
+    /**
+     * Whether the rotations counted since the last reset_counters stay within
+     * working_set_bound for accesses, which should be the keys splayed (by
+     * find, splay, and the rest) since that reset. The theorem says this
+     * always holds, so on real data it is a check of the implementation and
+     * a way to see how much slack the bound leaves.
+     */
+    pub fn within_working_set_bound(&self, accesses: &[K]) -> bool where K: PartialEq {
+        self.rotations as f64 <= self.working_set_bound(accesses)
+    }
+
+    // Number of edges on the longest root-to-leaf path. A single node has
+    // height 0, and so does an empty tree.
+    pub fn height(&self) -> usize {
+        let mut height = 0;
+        let mut stack: Vec<(NodeId, usize)> = self.root.map(|r| (r, 0)).into_iter().collect();
+        while let Some((i, depth)) = stack.pop() {
+            height = height.max(depth);
+            let node = &self.nodes[i];
+            stack.extend(node.children[LEFT].map(|l| (l, depth + 1)));
+            stack.extend(node.children[RIGHT].map(|r| (r, depth + 1)));
+        }
+        height
+    }
+
+    // Whether the tree has degenerated into a single path, every node with at
+    // most one child, as new(n) builds and a run of sorted accesses can
+    // rebuild; then a search may cost O(n). Trees of 0 or 1 nodes count.
+    pub fn is_sorted_path(&self) -> bool {
+        self.len <= 1 || self.height() == self.len - 1
+    }
+
+    /**
+     * Number of nodes at each depth: entry d counts the nodes d edges below
+     * the root, so the vector is height() + 1 long, or empty for an empty
+     * tree. A path such as new(n) gives n ones; splaying its deepest node
+     * about halves its depth, which shows up as a shorter, fatter histogram.
+     */
+    pub fn balance_factor_histogram(&self) -> Vec<usize> {
+        let mut counts = Vec::new();
+        let mut stack: Vec<(NodeId, usize)> = self.root.map(|r| (r, 0)).into_iter().collect();
+        while let Some((i, depth)) = stack.pop() {
+            if depth == counts.len() {
+                counts.push(0);
+            }
+            counts[depth] += 1;
+            let node = &self.nodes[i];
+            stack.extend(node.children[LEFT].map(|l| (l, depth + 1)));
+            stack.extend(node.children[RIGHT].map(|r| (r, depth + 1)));
+        }
+        counts
+    }
+
+    // Number of edges between the node at idx and the root
+    pub fn depth(&self, idx: NodeId) -> usize {
+        let mut depth = 0;
+        let mut cur = idx;
+        while let Some(p) = self.nodes[cur].parent {
+            depth += 1;
+            cur = p;
+        }
+        depth
+    }
+
+    /**
+     * depth for a tree that may be corrupt, for debugging: the number of
+     * edges from idx up to the root, following parent pointers at most len()
+     * times. A valid tree never needs more, so going further means the
+     * pointers loop and the result is a Cycle error instead of a hang. A
+     * parent index past the end of the arena is an OutOfRange error, and so
+     * is idx itself; a removed idx is a Removed error, as its parent link
+     * may be stale.
+     */
+    pub fn path_len_to_root(&self, idx: NodeId) -> Result<usize, SplayError> {
+        self.check_index(idx)?;
+        let mut depth = 0;
+        let mut cur = idx;
+        loop {
+            let node = self.nodes.get(cur.0).ok_or(SplayError::OutOfRange { index: cur, len: self.nodes.len() })?;
+            match node.parent {
+                None => return Ok(depth),
+                Some(_) if depth >= self.len => return Err(SplayError::Cycle { index: idx }),
+                Some(p) => {
+                    depth += 1;
+                    cur = p;
+                }
+            }
+        }
+    }
+
+    // Splays the k-th smallest node (counting from 0) to the root and returns
+    // its index, or None if the tree has k or fewer nodes
+    pub fn select(&mut self, k: usize) -> Option<NodeId> {
+        if k >= self.len {
+            return None;
+        }
+        let idx = self.at_rank(k);
+        Some(self.splay(Some(idx)))
+    }
+
+    // Index of the node with k smaller keys, found from sizes without
+    // splaying. k must be less than len().
+    fn at_rank(&self, mut k: usize) -> NodeId {
+        let mut cur = self.root.unwrap();
+        loop {
+            let node = &self.nodes[cur];
+            let below = self.subtree_size(node.children[LEFT]);
+            match k.cmp(&below) {
+                Ordering::Less => cur = node.children[LEFT].unwrap(),
+                Ordering::Equal => return cur,
+                Ordering::Greater => {
+                    k -= below + 1;
+                    cur = node.children[RIGHT].unwrap();
+                }
+            }
+        }
+    }
+
+    // The k-th smallest key (counting from 0) and its value, splayed to the
+    // root as select does, or None if the tree has k or fewer nodes
+    pub fn access_by_rank(&mut self, k: usize) -> Option<(&K, &V)> {
+        let idx = self.select(k)?;
+        let (key, value) = self.nodes[idx].entry.as_ref().unwrap();
+        Some((key, value))
+    }
+
+    /**
+     * Splits the tree by position rather than by key: the first k nodes in
+     * sorted order go to the left tree and the rest to the right, so k = 0
+     * or k >= len() leaves one side empty. The k-th node is selected and
+     * splayed to the root, and its left subtree cut off. As with split, both
+     * halves get fresh arenas.
+     */
+    pub fn split_at_index(mut self, k: usize) -> (Self, Self) where C: Clone {
+        let r = match self.select(k) {
+            None => return (self.take_subtree(self.root), self.empty_like()),
+            Some(r) => r
+        };
+        let left = self.nodes[r].children[LEFT].take();
+        self.update_size(r);
+        (self.take_subtree(left), self.take_subtree(Some(r)))
+    }
+
+    /**
+     * split_at_index, checked: the first k nodes go left, or all of them if
+     * there are fewer, and each half's root size must agree with its len()
+     * and with that count. Panics otherwise, which points at a rotation or
+     * split that left a size stale. The checks are O(1).
+     */
+    pub fn split_by_rank(self, k: usize) -> (Self, Self) where C: Clone {
+        let n = self.len;
+        let (left, right) = self.split_at_index(k);
+        let want = (k.min(n), n - k.min(n));
+        let sizes = (left.subtree_size(left.root), right.subtree_size(right.root));
+        assert_eq!(sizes, want, "split_by_rank({}) of {} nodes: root sizes are off", k, n);
+        assert_eq!((left.len, right.len), want, "split_by_rank({}) of {} nodes: lengths are off", k, n);
+        (left, right)
+    }
+
+    /**
+     * Appends other's nodes after this tree's in inorder, whatever their
+     * keys: the positional counterpart of split_at_index, as join is of
+     * split. The last node is splayed to the root and other hung as its
+     * right subtree, with other's indices shifted up by this tree's arena
+     * size.
+     *
+     * If the keys do not increase across the seam, the result is a sequence
+     * rather than a search tree: splay, select, split_at_index, pop_min,
+     * pop_max, and the iterators still work by position, and
+     * check_invariants reports the first key out of order. Keyed operations
+     * (find, insert, remove, split, join) may go the wrong way, and in this
+     * crate's debug test builds they panic in verify_bst_order.
+     */
+    pub fn concat(&mut self, other: Self) {
+        self.hang_after(other);
+    }
+
+    // Index of the root, which is the node most recently splayed, or None if
+    // the tree is empty
+    pub fn root_index(&self) -> Option<NodeId> {
+        self.root
+    }
+
+    // Key at the root, the most recently accessed one, read without splaying
+    pub fn root_key(&self) -> Option<&K> {
+        self.root.map(|r| self.nodes[r].key())
+    }
+
+    // Index of the node with the smallest key, or None if the tree is empty.
+    // Unlike min, this only reads the tree and does not splay.
+    pub fn first(&self) -> Option<NodeId> {
+        self.root.map(|r| self.leftmost(r))
+    }
+
+    // Index of the node with the largest key; the read-only counterpart of max
+    pub fn last(&self) -> Option<NodeId> {
+        self.root.map(|r| self.rightmost(r))
+    }
+
+    // Splays the node with the smallest key to the root and returns its
+    // index, or None if the tree is empty
+    pub fn min(&mut self) -> Option<NodeId> {
+        let idx = self.leftmost(self.root?);
+        Some(self.splay(Some(idx)))
+    }
+
+    // Splays the node with the largest key to the root and returns its
+    // index, or None if the tree is empty
+    pub fn max(&mut self) -> Option<NodeId> {
+        let idx = self.rightmost(self.root?);
+        Some(self.splay(Some(idx)))
+    }
+
+    /**
+     * Relinks the nodes into a tree of minimum height with the same inorder
+     * sequence, in O(n). Nodes keep their indices and the arena is reused;
+     * each subtree is rooted at the middle of its stretch of keys:
+     *
+     * ```text
+     *         4                 2
+     *        /                /   \
+     *       3      ====>     1     4
+     *      /                /     /
+     *     2                0     3
+     *    /
+     *   1
+     *  /
+     * 0
+     * ```
+     */
+    pub fn rebalance(&mut self) {
+        let order = self.inorder_indices();
+        let root = self.build_balanced(&order, None);
+        self.root = root;
+    }
+
+    /**
+     * Balances the tree using only splay steps, as an alternative to
+     * rebalance that stays within the splay model. The median is splayed to
+     * the root, then the median of each side splayed up until it is the
+     * root's child on that side, and so on down, level by level. Every
+     * subtree's root ends up being its median, so the result is as balanced
+     * as rebalance makes it. Costs O(n log n) amortized, for n splays.
+     *
+     * Returns the height of the tree afterwards.
+     */
+    pub fn splay_all_to_balanced(&mut self) -> usize {
+        // (node the subtree hangs from, range of ranks in the subtree)
+        let mut work = vec![(None, 0, self.len)];
+        while let Some((goal, lo, hi)) = work.pop() {
+            if lo == hi {
+                continue;
+            }
+            let mid = lo + (hi - lo) / 2;
+            let x = self.at_rank(mid);
+            self.splay_below(x, goal);
+            work.push((Some(x), lo, mid));
+            work.push((Some(x), mid + 1, hi));
+        }
+        self.height()
+    }
+
+    // Splays x up until its parent is goal, which must be one of its
+    // ancestors, or to the root if goal is None. The last step is a zig if
+    // x is then a grandchild of goal.
+    fn splay_below(&mut self, x: NodeId, goal: Option<NodeId>) {
+        while let Some(y) = self.nodes[x].parent.filter(|&y| Some(y) != goal) {
+            if self.nodes[y].parent == goal {
+                let dir = self.side(x);
+                self.splay_steps += 1;
+                self.rotate(y, dir);
+            }
+            else {
+                self.splay_step(Some(x));
+            }
+        }
+    }
+
+    /**
+     * Removes every key for which f returns false, visiting keys in sorted
+     * order. Rather than deleting one key at a time, this collects the
+     * survivors in a single inorder pass and relinks them as in rebalance,
+     * for O(n) overall. Removed slots go on the free list and the survivors
+     * keep their indices.
+     */
+    pub fn retain<F: FnMut(&K) -> bool>(&mut self, mut f: F) {
+        let mut kept = Vec::with_capacity(self.len);
+        for i in self.inorder_indices() {
+            if f(self.nodes[i].key()) {
+                kept.push(i);
+            }
+            else {
+                self.nodes[i].entry = None;
+                self.free.push(i);
+                self.len -= 1;
+            }
+        }
+        self.root = self.build_balanced(&kept, None);
+    }
+
+    /**
+     * Moves the live nodes down into a dense prefix of the arena, keeping
+     * their relative order and the tree's shape, then gives the unused
+     * memory back. Costs O(n) and is only done when called. Every node whose
+     * index was past a removed one gets a new index, so earlier handles
+     * should be looked up again.
+     */
+    pub fn shrink_to_fit(&mut self) {
+        let mut new_index = vec![None; self.nodes.len()];
+        let mut next = 0;
+        for (i, node) in self.nodes.iter().enumerate() {
+            if node.entry.is_some() {
+                new_index[i] = Some(NodeId(next));
+                next += 1;
+            }
+        }
+        let remap = |i: Option<NodeId>| i.and_then(|i| new_index[i.0]);
+
+        let nodes = std::mem::take(&mut self.nodes);
+        self.nodes = nodes.into_iter().filter(|node| node.entry.is_some()).map(|node| Node{
+            parent: remap(node.parent),
+            children: node.children.map(remap),
+            ..node
+        }).collect();
+        self.root = remap(self.root);
+        self.free.clear();
+        self.nodes.shrink_to_fit();
+        self.free.shrink_to_fit();
+    }
+
+    // Links the nodes in order (an inorder sequence) into a balanced subtree
+    // under parent and returns its root. Recursion depth is O(log n).
+    fn build_balanced(&mut self, order: &[NodeId], parent: Option<NodeId>) -> Option<NodeId> {
+        if order.is_empty() {
+            return None;
+        }
+        let mid = order.len() / 2;
+        let idx = order[mid];
+
+        let left = self.build_balanced(&order[..mid], Some(idx));
+        let right = self.build_balanced(&order[mid + 1..], Some(idx));
+        let node = &mut self.nodes[idx];
+        node.parent = parent;
+        node.children[LEFT] = left;
+        node.children[RIGHT] = right;
+        node.size = order.len();
+        Some(idx)
+    }
+
+    /**
+     * A tree with the same keys, shape, and indices, and each value passed
+     * through f, like Option::map. Nothing is searched or splayed; f is
+     * called once per value in key order. The counters and free slots carry
+     * over too, so the result is this tree with new payloads.
+     */
+    pub fn map_values<U, F: FnMut(V) -> U>(mut self, mut f: F) -> SplayTree<K, U, C> {
+        let mut mapped: Vec<Option<(K, U)>> = self.nodes.iter().map(|_| None).collect();
+        for i in self.inorder_indices() {
+            let (key, value) = self.nodes[i].entry.take().unwrap();
+            mapped[i.0] = Some((key, f(value)));
+        }
+        let nodes = self.nodes.into_iter().zip(mapped).map(|(node, entry)| Node{
+            entry,
+            parent: node.parent,
+            children: node.children,
+            size: node.size,
+            accesses: node.accesses
+        }).collect();
+        SplayTree {
+            root: self.root,
+            nodes,
+            free: self.free,
+            len: self.len,
+            rotations: self.rotations,
+            splay_steps: self.splay_steps,
+            cmp: self.cmp
+        }
+    }
+
+    // Indices of the nodes in sorted key order, for quick checks in tests
+    // and debugging; the tree is left as it is
+    pub fn dump_sorted(&self) -> Vec<NodeId> {
+        self.inorder_indices()
+    }
+
+    // The keys in sorted order, copied out, as dump_sorted does for indices
+    pub fn dump_sorted_keys(&self) -> Vec<K> where K: Clone {
+        self.keys().cloned().collect()
+    }
+
+    // Indices of the live nodes in sorted key order
+    fn inorder_indices(&self) -> Vec<NodeId> {
+        let mut order = Vec::with_capacity(self.len);
+        let mut stack = Vec::new();
+        let mut cur = self.root;
+        while cur.is_some() || !stack.is_empty() {
+            while let Some(i) = cur {
+                stack.push(i);
+                cur = self.nodes[i].children[LEFT];
+            }
+            let i = stack.pop().unwrap();
+            order.push(i);
+            cur = self.nodes[i].children[RIGHT];
+        }
+        order
+    }
+
+    // main interface function
+    // Splays x to the root and returns its index, which is now the root.
+    // Splaying the node that is already the root is a no-op.
+    // Panics if x is not a node of the tree (see try_splay).
+    pub fn splay(&mut self, x_idx: Option<NodeId>) -> NodeId {
+        assert!(x_idx.is_some());
+        match self.try_splay(x_idx.unwrap()) {
+            Ok(root) => root,
+            Err(e) => panic!("{}", e)
+        }
+    }
+
+    // Splays each index in idxs in turn, as a single call for replaying an
+    // access sequence. Returns the number of rotations the sequence took;
+    // an index that is already the root costs none.
+    pub fn bulk_splay(&mut self, idxs: &[NodeId]) -> u64 {
+        let before = self.rotations;
+        for &idx in idxs {
+            self.splay(Some(idx));
+        }
+        self.rotations - before
+    }
+
+    // Like splay, but reports an index that is not in the tree instead of panicking.
+    // An empty tree has no valid index, so every call on one is an error.
+    pub fn try_splay(&mut self, idx: NodeId) -> Result<NodeId, SplayError> {
+        self.check_index(idx)?;
+        self.nodes[idx].accesses += 1;
+        while self.root != Some(idx) {
+            self.splay_step(Some(idx));
+        }
+        Ok(idx)
+    }
+
+    fn set_root(&mut self, x_idx: Option<NodeId>) {
+        assert!(x_idx.is_some());
+
+        self.root = x_idx;
+        let x = &mut self.nodes[x_idx.unwrap()];
+        x.parent = None;
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, C> fmt::Debug for SplayTree<K, V, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        match self.root {
+            None => writeln!(f, "root: none \nnodes:")?,
+            Some(x) => writeln!(f, "root: {} \nnodes:", x)?
+        }
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let (key, value) = match &node.entry {
+                None => { writeln!(f, "index: {}, removed", i)?; continue; },
+                Some(entry) => entry
+            };
+            write!(f, "index: {}, key: {:?}, value: {:?}, ", i, key, value)?;
+            match node.parent {
+                None => write!(f, "parent: none, ")?,
+                Some(x) => write!(f, "parent: {}, ", x)?
+            }
+            match node.children[LEFT] {
+                None => write!(f, "left: none, ")?,
+                Some(x) => write!(f, "left: {}, ", x)?
+            }
+            match node.children[RIGHT] {
+                None => writeln!(f, "right: none")?,
+                Some(x) => writeln!(f, "right: {}", x)?
+            }
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Draws the keys sideways with the root on the left and each child indented
+ * one level under its parent, tagged with which side it hangs off:
+ *
+ * ```text
+ * 2
+ *   L: 0
+ *     R: 1
+ *   R: 3
+ * ```
+ */
+impl<K: fmt::Display, V, C> fmt::Display for SplayTree<K, V, C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        let root = match self.root {
+            None => return writeln!(f, "(empty)"),
+            Some(x) => x
+        };
+
+        // explicit stack so that long paths don't overflow the call stack
+        let mut stack = vec![(root, 0, "")];
+        while let Some((i, depth, side)) = stack.pop() {
+            let node = &self.nodes[i];
+            writeln!(f, "{:indent$}{}{}", "", side, node.key(), indent = 2 * depth)?;
+
+            if let Some(r) = node.children[RIGHT] {
+                stack.push((r, depth + 1, "R: "));
+            }
+            if let Some(l) = node.children[LEFT] {
+                stack.push((l, depth + 1, "L: "));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K: fmt::Display, V, C> SplayTree<K, V, C> {
+
+    /**
+     * Graphviz source for the tree, for rendering with dot -Tpng. Each live
+     * node is drawn as n<index> labeled with its key, with edges to its
+     * children labeled L and R; the root is filled in. Removed slots are
+     * left out.
+     */
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph splay_tree {\n    node [shape=circle];\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let (key, _) = match &node.entry {
+                None => continue,
+                Some(entry) => entry
+            };
+            let label = key.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+            let style = if self.root == Some(NodeId(i)) { ", style=filled, fillcolor=lightblue" } else { "" };
+            dot += &format!("    n{} [label=\"{}\"{}];\n", i, label, style);
+            for (dir, side) in [(LEFT, "L"), (RIGHT, "R")] {
+                if let Some(c) = node.children[dir] {
+                    dot += &format!("    n{} -> n{} [label=\"{}\"];\n", i, c, side);
+                }
+            }
+        }
+        dot + "}\n"
+    }
+}
+
+/**
+ * Collects keys into a tree. Input that is already strictly increasing is
+ * laid out directly as a path in O(n); anything else is inserted one key at
+ * a time, which costs O(n log n) amortized and drops duplicate keys.
+ */
+impl<K: Ord> FromIterator<K> for SplayTree<K> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let keys: Vec<K> = iter.into_iter().collect();
+        if keys.windows(2).all(|w| w[0] < w[1]) {
+            return Self::from_sorted_keys(keys);
+        }
+
+        let mut tree = Self::from_sorted_keys(Vec::new());
+        tree.extend(keys);
+        tree
+    }
+}
+
+/**
+ * Builds a tree from keys in any order. They are sorted and duplicates
+ * dropped first, in O(n log n), so the result is always the path that
+ * from_sorted_keys lays out; a Vec already sorted and free of duplicates can
+ * go to from_sorted_keys directly instead, in O(n).
+ */
+impl<K: Ord> From<Vec<K>> for SplayTree<K> {
+    fn from(mut keys: Vec<K>) -> Self {
+        keys.sort_unstable();
+        keys.dedup();
+        Self::from_sorted_keys(keys)
+    }
+}
+
+// The keys in sorted order, the tree's own order for one built with_comparator
+impl<K, C> From<SplayTree<K, (), C>> for Vec<K> {
+    fn from(tree: SplayTree<K, (), C>) -> Self {
+        tree.into_keys().collect()
+    }
+}
+
+// Inserts each key in turn; a key already in the tree is left where it is
+impl<K, C: Compare<K>> Extend<K> for SplayTree<K, (), C> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+/**
+ * Trees are equal when they hold the same (key, value) pairs, whatever
+ * their shapes: splaying never changes what a tree holds, so trees built or
+ * accessed in different orders still compare equal. Indices, counters, and
+ * free slots are ignored too.
+ */
+impl<K: PartialEq, V: PartialEq, C> PartialEq for SplayTree<K, V, C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.inorder_indices().into_iter().zip(other.inorder_indices()).all(|(i, j)| {
+            self.nodes[i].entry == other.nodes[j].entry
+        })
+    }
+}
+
+impl<K: Eq, V: Eq, C> Eq for SplayTree<K, V, C> {}
+
+// Hashes the (key, value) pairs in sorted order, so that trees equal under
+// PartialEq hash the same whatever their shapes. Like eq, this walks the
+// whole tree, in O(n).
+impl<K: Hash, V: Hash, C> Hash for SplayTree<K, V, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for (key, value) in self.iter() {
+            key.hash(state);
+            value.hash(state);
+        }
+    }
+}
+
+// An empty tree, with nothing allocated
+impl<K, V> Default for SplayTree<K, V> {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Shape = (Option<NodeId>, Vec<(Option<NodeId>, Option<NodeId>, Option<NodeId>)>);
+
+    fn shape(tree: &SplayTree<usize>) -> Shape {
+        (tree.root, tree.nodes.iter().map(|n| (n.parent, n.children[LEFT], n.children[RIGHT])).collect())
+    }
+
+    #[test]
+    fn splay_root_is_noop() {
+        let mut tree = SplayTree::new(5);
+        let before = shape(&tree);
+        let root = tree.root;
+
+        tree.splay(root);
+        tree.splay(root);
+        assert_eq!(shape(&tree), before);
+    }
+
+    #[test]
+    fn find_splays_hit_to_root() {
+        let mut tree = SplayTree::new(10);
+        for k in [3, 9, 0, 5, 5] {
+            assert_eq!(tree.find(&k), Some(NodeId(k)));
+            assert_eq!(tree.root, Some(NodeId(k)));
+        }
+    }
+
+    #[test]
+    fn find_splays_last_node_on_miss() {
+        let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+        assert_eq!(tree.find(&25), None);
+        let root = tree.root.unwrap();
+        assert!(*tree.nodes[root].key() == 20 || *tree.nodes[root].key() == 30);
+
+        assert_eq!(tree.find(&5), None);
+        assert_eq!(*tree.nodes[tree.root.unwrap()].key(), 10);
+        assert_eq!(SplayTree::new(0).find(&0), None);
+    }
+
+    fn inorder<K: Clone, V>(tree: &SplayTree<K, V>) -> Vec<K> {
+        let mut keys = Vec::new();
+        let mut stack = Vec::new();
+        let mut cur = tree.root;
+        while cur.is_some() || !stack.is_empty() {
+            while let Some(i) = cur {
+                stack.push(i);
+                cur = tree.nodes[i].children[LEFT];
+            }
+            let i = stack.pop().unwrap();
+            keys.push(tree.nodes[i].key().clone());
+            cur = tree.nodes[i].children[RIGHT];
+        }
+        keys
+    }
+
+    #[test]
+    fn remove_root_leaf_and_internal() {
+        let mut tree = SplayTree::new(10);
+        tree.splay(Some(NodeId(5)));
+
+        assert!(tree.remove(&5));      // root
+        assert_eq!(inorder(&tree), vec![0, 1, 2, 3, 4, 6, 7, 8, 9]);
+        assert!(tree.remove(&9));      // leaf
+        assert_eq!(inorder(&tree), vec![0, 1, 2, 3, 4, 6, 7, 8]);
+        assert!(tree.remove(&2));      // internal
+        assert_eq!(inorder(&tree), vec![0, 1, 3, 4, 6, 7, 8]);
+
+        assert!(!tree.remove(&2));
+        assert_eq!(tree.len(), 7);
+        assert_eq!(*tree.key(NodeId(7)), 7);
+        assert_eq!(tree.try_splay(NodeId(2)), Err(SplayError::Removed { index: NodeId(2) }));
+    }
+
+    #[test]
+    fn remove_down_to_empty() {
+        let mut tree = SplayTree::new(3);
+        for k in [1, 0, 2] {
+            assert!(tree.remove(&k));
+        }
+        assert!(tree.is_empty());
+        assert_eq!(tree.root, None);
+        assert!(!tree.remove(&0));
+    }
+
+    #[test]
+    fn insert_reuses_removed_slots() {
+        let mut tree = SplayTree::new(8);
+        for k in [1, 4, 6] {
+            assert!(tree.remove(&k));
+        }
+        assert_eq!(tree.len(), 5);
+
+        let mut reused = vec![tree.insert(10), tree.insert(11), tree.insert(12)];
+        reused.sort();
+        assert_eq!(reused, [1, 4, 6].map(NodeId));
+        assert_eq!(tree.nodes.len(), 8);
+        assert_eq!(tree.len(), 8);
+
+        for k in [0, 2, 3, 5, 7] {
+            assert_eq!(*tree.key(NodeId(k)), k);
+        }
+        assert_eq!(tree.insert(13), NodeId(8));
+        assert_eq!(inorder(&tree), vec![0, 2, 3, 5, 7, 10, 11, 12, 13]);
+    }
+
+    #[test]
+    fn shrink_to_fit_compacts_the_arena() {
+        let mut tree = SplayTree::from_sorted_pairs((0..20).map(|k| (k, k * 2)).collect());
+        tree.splay(Some(NodeId(9)));
+        for k in (0..20).filter(|k| k % 2 == 1) {
+            assert!(tree.remove(&k));
+        }
+        tree.shrink_to_fit();
+        assert_eq!(tree.nodes.len(), tree.len());
+        assert!(tree.free.is_empty());
+        assert_eq!(tree.check_invariants(), Ok(()));
+        assert_eq!(inorder(&tree), (0..20).step_by(2).collect::<Vec<_>>());
+        assert_eq!(*tree.key(NodeId(3)), 6);
+        assert_eq!(tree.get(&6), Some(&12));
+
+        // nothing to compact
+        let mut full = SplayTree::new(5);
+        let before = shape(&full);
+        full.shrink_to_fit();
+        assert_eq!(shape(&full), before);
+        assert_eq!(tree.insert_pair(7, 14), NodeId(10));
+        assert_eq!(tree.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn split_at_each_key() {
+        for k in 0..10 {
+            let (left, right) = SplayTree::new(10).split(&k);
+            assert_eq!(inorder(&left), (0..=k).collect::<Vec<_>>());
+            assert_eq!(inorder(&right), (k + 1..10).collect::<Vec<_>>());
+            assert_eq!(left.len() + right.len(), 10);
+            assert_eq!(left.nodes.len(), left.len());
+        }
+    }
+
+    #[test]
+    fn split_at_absent_keys() {
+        let tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+        let (left, right) = tree.clone().split(&25);
+        assert_eq!(inorder(&left), vec![10, 20]);
+        assert_eq!(inorder(&right), vec![30, 40]);
+
+        let (left, right) = tree.clone().split(&5);
+        assert!(left.is_empty());
+        assert_eq!(inorder(&right), vec![10, 20, 30, 40]);
+
+        let (left, right) = SplayTree::new(0).split(&5);
+        assert!(left.is_empty() && right.is_empty());
+    }
+
+    #[test]
+    fn join_two_trees() {
+        let mut left = SplayTree::new(5);
+        left.splay(Some(NodeId(2)));
+        let right = SplayTree::from_sorted_keys((5..10).collect());
+
+        let tree = SplayTree::join(left, right);
+        assert_eq!(inorder(&tree), (0..10).collect::<Vec<_>>());
+        assert_eq!(tree.len(), 10);
+        assert_eq!(*tree.key(NodeId(7)), 7);
+        assert_eq!(tree.root, Some(NodeId(4)));
+    }
+
+    #[test]
+    fn join_with_empty() {
+        let tree = SplayTree::join(SplayTree::new(0), SplayTree::new(3));
+        assert_eq!(inorder(&tree), vec![0, 1, 2]);
+
+        let tree = SplayTree::join(SplayTree::new(3), SplayTree::new(0));
+        assert_eq!(inorder(&tree), vec![0, 1, 2]);
+
+        let tree = SplayTree::join(SplayTree::new(0), SplayTree::new(0));
+        assert!(tree.is_empty());
+        assert_eq!(tree.root, None);
+    }
+
+    // Checks every stored size against a fresh count of the subtree below it
+    fn assert_sizes<K, V>(tree: &SplayTree<K, V>) {
+        for (i, node) in tree.nodes.iter().enumerate() {
+            if node.entry.is_none() {
+                continue;
+            }
+            let mut count = 0;
+            let mut stack = vec![NodeId(i)];
+            while let Some(j) = stack.pop() {
+                count += 1;
+                stack.extend(tree.nodes[j].children[LEFT]);
+                stack.extend(tree.nodes[j].children[RIGHT]);
+            }
+            assert_eq!(node.size, count, "wrong size at node {}", i);
+        }
+    }
+
+    #[test]
+    fn sizes_follow_splays() {
+        let mut tree = SplayTree::new(10);
+        assert_sizes(&tree);
+        for idx in [5, 0, 9, 3, 3, 7, 1] {
+            tree.splay(Some(NodeId(idx)));
+            assert_sizes(&tree);
+            assert_eq!(tree.subtree_size(tree.root), 10);
+        }
+    }
+
+    #[test]
+    fn sizes_follow_updates() {
+        let mut tree = SplayTree::new(10);
+        tree.insert(20);
+        tree.insert(15);
+        assert_sizes(&tree);
+        tree.remove(&4);
+        tree.remove(&20);
+        assert_sizes(&tree);
+
+        let (left, right) = tree.split(&6);
+        assert_sizes(&left);
+        assert_sizes(&right);
+        let tree = SplayTree::join(left, right);
+        assert_sizes(&tree);
+        assert_eq!(tree.subtree_size(tree.root), 10);
+    }
+
+    #[test]
+    fn check_invariants_catches_corruption() {
+        let mut tree = SplayTree::new(10);
+        tree.splay(Some(NodeId(4)));
+        tree.remove(&7);
+        assert_eq!(tree.check_invariants(), Ok(()));
+
+        let mut bad = tree.clone();
+        bad.nodes[3].parent = Some(NodeId(8));
+        assert_eq!(bad.check_invariants(), Err("node 3 is the left child of 4 but has parent Some(8)".to_string()));
+
+        let mut bad = tree.clone();
+        bad.nodes[2].children[LEFT] = Some(NodeId(4));
+        bad.nodes[4].parent = Some(NodeId(2));
+        assert!(bad.check_invariants().unwrap_err().contains("node 4"));
+
+        let mut bad = tree.clone();
+        bad.nodes[2].entry = Some((9, ()));
+        assert!(bad.check_invariants().unwrap_err().contains("node 3"));
+
+        let mut bad = tree.clone();
+        bad.free.clear();
+        assert_eq!(bad.check_invariants(), Err("node 7 is neither reachable nor free".to_string()));
+
+        let mut bad = tree.clone();
+        bad.nodes[1].size = 1;
+        assert_eq!(bad.check_invariants(), Err("node 1 has size 1 but its subtree has 2 nodes".to_string()));
+    }
+
+    #[test]
+    fn validate_sizes_blames_the_corrupted_node() {
+        let mut tree = SplayTree::new(16);
+        tree.splay(Some(NodeId(0)));
+        tree.splay(Some(NodeId(9)));
+        assert!(tree.validate_sizes());
+
+        // a node whose parent has a smaller index, so a scan in index order
+        // comparing against children's stored sizes would blame the parent
+        let i = (0..16).map(NodeId).find(|&i| tree.nodes[i].parent.is_some_and(|p| p < i)).unwrap();
+        let true_size = tree.nodes[i].size;
+        tree.nodes[i].size += 3;
+        assert!(!tree.validate_sizes());
+        assert_eq!(tree.first_bad_size(), Some((i, true_size)));
+        assert_eq!(tree.check_invariants(), Err(format!("node {} has size {} but its subtree has {} nodes", i, true_size + 3, true_size)));
+    }
+
+    #[test]
+    fn path_len_to_root_stops_on_cycles() {
+        let mut tree = SplayTree::new(6);
+        tree.splay(Some(NodeId(2)));
+        for i in (0..6).map(NodeId) {
+            assert_eq!(tree.path_len_to_root(i), Ok(tree.depth(i)));
+        }
+
+        let mut bad = tree.clone();
+        bad.nodes[1].parent = Some(NodeId(0));
+        bad.nodes[0].parent = Some(NodeId(1));
+        assert_eq!(bad.path_len_to_root(NodeId(0)), Err(SplayError::Cycle { index: NodeId(0) }));
+        assert_eq!(bad.path_len_to_root(NodeId(3)), Ok(tree.depth(NodeId(3))));
+
+        let mut bad = tree.clone();
+        bad.nodes[5].parent = Some(NodeId(5));
+        assert_eq!(bad.path_len_to_root(NodeId(5)).unwrap_err().to_string(), "parent pointers from node 5 form a cycle");
+
+        let mut bad = tree.clone();
+        bad.nodes[0].parent = Some(NodeId(40));
+        assert_eq!(bad.path_len_to_root(NodeId(0)), Err(SplayError::OutOfRange { index: NodeId(40), len: 6 }));
+
+        // retain leaves a removed node's parent link in place
+        let mut removed = tree;
+        removed.retain(|&k| k != 3);
+        assert_eq!(removed.path_len_to_root(NodeId(3)), Err(SplayError::Removed { index: NodeId(3) }));
+        assert_eq!(removed.path_len_to_root(NodeId(6)), Err(SplayError::OutOfRange { index: NodeId(6), len: 6 }));
+    }
+
+    #[test]
+    fn parent_chain_stops_on_cycles() {
+        let mut tree = SplayTree::new(6);
+        tree.splay(Some(NodeId(2)));
+
+        let mut bad = tree.clone();
+        bad.nodes[1].parent = Some(NodeId(0));
+        bad.nodes[0].parent = Some(NodeId(1));
+        assert_eq!(bad.parent_chain(NodeId(0)).collect::<Vec<_>>(), [0, 1, 0, 1, 0, 1].map(NodeId));
+
+        let mut bad = tree;
+        bad.nodes[0].parent = Some(NodeId(40));
+        assert_eq!(bad.parent_chain(NodeId(0)).collect::<Vec<_>>(), [NodeId(0)]);
+    }
+
+    // xorshift, so tests can make long pseudo-random sequences without a dependency
+    fn next_rand(seed: &mut u64) -> u64 {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        *seed
+    }
+
+    // Textbook splay, deciding each case from explicit left/right checks
+    fn reference_splay(tree: &mut SplayTree<usize>, x: NodeId) {
+        let is_left = |tree: &SplayTree<usize>, i: NodeId| {
+            let p = tree.nodes[i].parent.unwrap();
+            tree.nodes[p].children[LEFT] == Some(i)
+        };
+        while let Some(y) = tree.nodes[x].parent {
+            let x_dir = if is_left(tree, x) { LEFT } else { RIGHT };
+            match tree.nodes[y].parent {
+                None => tree.rotate(y, x_dir),
+                Some(z) => {
+                    let y_dir = if is_left(tree, y) { LEFT } else { RIGHT };
+                    if (x_dir == LEFT) == (y_dir == LEFT) {
+                        tree.rotate(z, y_dir);
+                        tree.rotate(y, x_dir);
+                    }
+                    else {
+                        tree.rotate(y, x_dir);
+                        tree.rotate(z, y_dir);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn splay_matches_reference_on_random_sequences() {
+        let mut seed = 15451;
+        for n in [1, 2, 3, 10, 200] {
+            let mut tree = SplayTree::new(n);
+            let mut reference = SplayTree::new(n);
+            for _ in 0..500 {
+                let idx = NodeId((next_rand(&mut seed) % n as u64) as usize);
+                tree.splay(Some(idx));
+                reference_splay(&mut reference, idx);
+                assert_eq!(shape(&tree), shape(&reference), "splaying {} in new({})", idx, n);
+            }
+            assert_eq!(tree.rotation_count(), reference.rotation_count());
+        }
+    }
+
+    #[test]
+    fn top_down_matches_bottom_up_one_step() {
+        // a node at depth two or less is a single zig, zig-zig, or zig-zag,
+        // which both versions perform identically
+        let mut tree = SplayTree::new(15);
+        tree.rebalance();
+        for idx in (0..15).map(NodeId) {
+            if tree.depth(idx) > 2 {
+                continue;
+            }
+            let mut bottom_up = tree.clone();
+            let mut top_down = tree.clone();
+            bottom_up.splay(Some(idx));
+            top_down.splay_top_down(idx);
+            assert_eq!(shape(&bottom_up), shape(&top_down), "splaying {}", idx);
+            assert_sizes(&top_down);
+        }
+    }
+
+    #[test]
+    fn top_down_matches_bottom_up_root_and_order() {
+        let mut seed = 451;
+        let mut bottom_up = SplayTree::new(100);
+        let mut top_down = SplayTree::new(100);
+        for _ in 0..1000 {
+            let idx = (next_rand(&mut seed) % 100) as usize;
+            bottom_up.splay(Some(NodeId(idx)));
+            top_down.splay_top_down(NodeId(idx));
+            assert_eq!(top_down.root, bottom_up.root);
+            assert_eq!(top_down.check_invariants(), Ok(()));
+        }
+        assert_sizes(&top_down);
+        assert_eq!(inorder(&top_down), inorder(&bottom_up));
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = SplayTree::new(0);
+        assert_eq!(tree.root, None);
+        assert!(tree.nodes.is_empty());
+
+        tree.print();
+        assert_eq!(shape(&tree), (None, vec![]));
+    }
+
+    #[test]
+    fn single_node_tree() {
+        let mut tree = SplayTree::new(1);
+        assert_eq!(shape(&tree), (Some(NodeId(0)), vec![(None, None, None)]));
+
+        assert_eq!(tree.splay(Some(NodeId(0))), NodeId(0));
+        assert_eq!(shape(&tree), (Some(NodeId(0)), vec![(None, None, None)]));
+    }
+
+    #[test]
+    fn rotations_mirror() {
+        // new(7) is a left path; inserting 6 down to 0 builds its mirror, a
+        // right path where index i holds key 6 - i
+        let mut left = SplayTree::new(7);
+        let mut right = SplayTree::from_sorted_keys(vec![]);
+        for k in (0..7).rev() {
+            right.insert(k);
+        }
+        let mirror = |(root, nodes): Shape| (root, nodes.into_iter().map(|(p, l, r)| (p, r, l)).collect::<Vec<_>>());
+        assert_eq!(shape(&right), mirror(shape(&left)));
+
+        // zig-zig and zig-zag steps in both directions
+        for i in [0, 3, 6, 2, 5, 1, 4] {
+            left.splay(Some(NodeId(i)));
+            right.splay(Some(NodeId(i)));
+            assert_eq!(shape(&right), mirror(shape(&left)));
+            assert_eq!(right.check_invariants(), Ok(()));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "rotate_right on 0: missing left child")]
+    fn rotate_right_without_left_child() {
+        SplayTree::new(3).rotate(NodeId(0), LEFT);
+    }
+
+    #[test]
+    #[should_panic(expected = "rotate_left on 2: missing right child")]
+    fn rotate_left_without_right_child() {
+        SplayTree::new(3).rotate(NodeId(2), RIGHT);
+    }
+
+    #[test]
+    fn concat_out_of_order_keys_stays_positional() {
+        let mut tree = SplayTree::from_sorted_keys(vec![5, 6, 7]);
+        tree.concat(SplayTree::from_sorted_keys(vec![1, 2, 3]));
+        assert_eq!(inorder(&tree), vec![5, 6, 7, 1, 2, 3]);
+        assert_eq!(tree.check_invariants(), Err("key of node 3 is not greater than its predecessor 2".to_string()));
+
+        // positional operations skip the key-order hook, even in a debug build
+        let idx = tree.select(4).unwrap();
+        assert_eq!(*tree.key(idx), 2);
+        assert_eq!(tree.pop_min(), Some(5));
+        assert_eq!(tree.pop_max(), Some(3));
+        let (left, right) = tree.split_at_index(2);
+        assert_eq!((inorder(&left), inorder(&right)), (vec![6, 7], vec![1, 2]));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "verify_bst_order: key of node 3 is not greater than its predecessor 2")]
+    fn verify_bst_order_fires_in_the_corrupting_operation() {
+        let mut tree = SplayTree::new(8);
+        assert_eq!(tree.find(&0), Some(NodeId(0)));
+
+        // swap two keys behind the tree's back, as a buggy rotation might;
+        // the next find panics instead of returning
+        let (a, b) = (tree.nodes[NodeId(2)].entry.take(), tree.nodes[NodeId(5)].entry.take());
+        tree.nodes[NodeId(2)].entry = b;
+        tree.nodes[NodeId(5)].entry = a;
+        tree.find(&0);
+    }
+}
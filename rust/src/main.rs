@@ -0,0 +1,18 @@
+/*
+* main.rs
+*
+* Small demo of the splay_tree library: builds a path-shaped tree and splays
+* a node from the middle of it.
+*/
+
+use splay_tree::{NodeId, SplayTree};
+
+fn main() {
+    let mut tree: SplayTree<usize> = SplayTree::new(10);
+    tree.print();
+
+    println!("splaying 5 ----------");
+    let root = tree.splay(Some(NodeId::from_index(5)));
+    println!("new root: {}", root);
+    tree.print();
+}
@@ -0,0 +1,170 @@
+/*
+* rope.rs
+*
+* A text buffer on top of SeqSplay, the way an editor might hold a file being
+* edited. The bytes of the text are the elements of the sequence, so an edit
+* anywhere isolates the spot with two splays and costs O(log n) amortized plus
+* the length of the text inserted, removed, or read, instead of shifting
+* everything after it as a String does.
+*
+* Positions are byte offsets, as with str. Every edit must fall on a char
+* boundary, so the buffer is always valid UTF-8.
+*/
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::SeqSplay;
+
+pub struct SplayRope {
+    bytes: SeqSplay<u8>
+}
+
+impl SplayRope {
+
+    pub fn new() -> Self {
+        Self { bytes: SeqSplay::from_vec(Vec::new()) }
+    }
+
+    // Length in bytes
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    // Inserts s at byte offset pos. Panics if pos is past the end or not on
+    // a char boundary.
+    pub fn insert_str(&mut self, pos: usize, s: &str) {
+        self.assert_char_boundary(pos, "insert_str");
+        self.bytes.insert_at(pos, s.as_bytes().to_vec());
+    }
+
+    // Removes the bytes in range. Panics if either end is past the end or
+    // not on a char boundary.
+    pub fn delete(&mut self, range: Range<usize>) {
+        self.assert_range(&range, "delete");
+        self.bytes.remove_range(range.start, range.end);
+    }
+
+    // A copy of the text in range, with the same panics as delete
+    pub fn substring(&mut self, range: Range<usize>) -> String {
+        self.assert_range(&range, "substring");
+        String::from_utf8(self.bytes.slice(range.start, range.end)).expect("range is on char boundaries")
+    }
+
+    fn assert_range(&mut self, range: &Range<usize>, op: &str) {
+        assert!(range.start <= range.end, "{}: range {}..{} is backwards", op, range.start, range.end);
+        self.assert_char_boundary(range.start, op);
+        self.assert_char_boundary(range.end, op);
+    }
+
+    // A char boundary is the end of the text or any byte that does not
+    // continue a multi-byte char, i.e. is not 0b10xxxxxx
+    fn assert_char_boundary(&mut self, pos: usize, op: &str) {
+        let len = self.len();
+        assert!(pos <= len, "{}: byte index {} is out of bounds for length {}", op, pos, len);
+        if let Some(&b) = self.bytes.get(pos) {
+            assert!(b & 0xC0 != 0x80, "{}: byte index {} is not a char boundary", op, pos);
+        }
+    }
+}
+
+impl Default for SplayRope {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<&str> for SplayRope {
+    fn from(s: &str) -> Self {
+        Self { bytes: SeqSplay::from_vec(s.as_bytes().to_vec()) }
+    }
+}
+
+impl fmt::Display for SplayRope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = self.bytes.to_vec();
+        f.write_str(std::str::from_utf8(&bytes).expect("edits keep the text valid UTF-8"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_match_string() {
+        let mut rope = SplayRope::from("hello world");
+        let mut model = String::from("hello world");
+
+        for (pos, s) in [(5, ","), (0, ">> "), (14, "!"), (8, "big ")] {
+            rope.insert_str(pos, s);
+            model.insert_str(pos, s);
+            assert_eq!(rope.to_string(), model);
+        }
+        for range in [0..3, 4..8, 0..0] {
+            rope.delete(range.clone());
+            model.replace_range(range, "");
+            assert_eq!(rope.to_string(), model);
+        }
+        assert_eq!(rope.substring(0..5), model[0..5]);
+        assert_eq!(rope.substring(3..model.len()), model[3..]);
+        assert_eq!(rope.len(), model.len());
+        assert_eq!(rope.bytes.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn random_edits_match_string() {
+        let mut seed = 0x2545f4914f6cdd1d_u64;
+        let mut next = |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as usize % n
+        };
+
+        let mut rope = SplayRope::new();
+        let mut model = String::new();
+        for _ in 0..300 {
+            let pos = next(model.len() + 1);
+            if next(3) == 0 && pos < model.len() {
+                let end = pos + next(model.len() - pos + 1);
+                assert_eq!(rope.substring(pos..end), model[pos..end]);
+                rope.delete(pos..end);
+                model.replace_range(pos..end, "");
+            }
+            else {
+                let s = ["a", "bc", "def", ""][next(4)];
+                rope.insert_str(pos, s);
+                model.insert_str(pos, s);
+            }
+        }
+        assert_eq!(rope.to_string(), model);
+        assert_eq!(rope.bytes.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn multi_byte_chars() {
+        let mut rope = SplayRope::from("naïve");
+        rope.insert_str(4, "-");
+        assert_eq!(rope.to_string(), "naï-ve");
+        assert_eq!(rope.substring(2..4), "ï");
+        rope.delete(2..4);
+        assert_eq!(rope.to_string(), "na-ve");
+    }
+
+    #[test]
+    #[should_panic(expected = "insert_str: byte index 3 is not a char boundary")]
+    fn insert_inside_a_char() {
+        SplayRope::from("naïve").insert_str(3, "x");
+    }
+
+    #[test]
+    #[should_panic(expected = "delete: byte index 9 is out of bounds for length 5")]
+    fn delete_past_the_end() {
+        SplayRope::from("hello").delete(2..9);
+    }
+}
@@ -0,0 +1,626 @@
+/*
+* seq.rs
+*
+* A splay tree over a sequence instead of a key order. An element's "key" is
+* its position in the inorder traversal, which is found from subtree sizes,
+* so the sequence can be split and concatenated anywhere, new elements
+* inserted at any position, and any range of it reversed, removed, or read.
+* Reversal is lazy: it flags the root of the range's subtree, and the flag is
+* pushed down a level at a time by later searches through it.
+*/
+
+use crate::{LEFT, RIGHT};
+
+#[derive(Clone)]
+struct Node<T> {
+    value: Option<T>,       // the element; only None while a split is moving nodes
+    parent: Option<usize>,  // None, or Some(Index) into Vec<Nodes>
+    children: [Option<usize>; 2],   // [LEFT] and [RIGHT]: None, or Some(Index) into Vec<Nodes>
+    size: usize,            // number of nodes in the subtree rooted here
+    reversed: bool          // this subtree is still to be mirrored below this node
+}
+
+#[derive(Clone)]
+pub struct SeqSplay<T> {
+    root: Option<usize>,    // None for an empty sequence, or Some(Index) of root Node
+    nodes: Vec<Node<T>>     // vector of nodes, all of them live
+}
+
+impl<T> SeqSplay<T> {
+
+    // The sequence of values, in order, as a tree of minimum height
+    pub fn from_vec(values: Vec<T>) -> Self {
+        let mut seq = Self { root: None, nodes: Vec::with_capacity(values.len()) };
+        for value in values {
+            seq.nodes.push(Node{ value: Some(value), parent: None, children: [None, None], size: 1, reversed: false });
+        }
+        seq.root = seq.build_balanced(0, seq.nodes.len(), None);
+        seq
+    }
+
+    pub fn len(&self) -> usize {
+        self.subtree_size(self.root)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    // Splays the element at position pos to the root and returns it, or None
+    // if pos is past the end
+    pub fn get(&mut self, pos: usize) -> Option<&T> {
+        if pos >= self.len() {
+            return None;
+        }
+        let idx = self.locate(pos);
+        self.splay_to(idx, None);
+        self.nodes[idx].value.as_ref()
+    }
+
+    pub fn get_mut(&mut self, pos: usize) -> Option<&mut T> {
+        if pos >= self.len() {
+            return None;
+        }
+        let idx = self.locate(pos);
+        self.splay_to(idx, None);
+        self.nodes[idx].value.as_mut()
+    }
+
+    /**
+     * Reverses the elements at positions lo..hi in O(log n) amortized.
+     *
+     * The element before the range is splayed to the root and the one after
+     * it to the root's right child, which leaves exactly the range in a
+     * single subtree between them. Only that subtree's root is flagged:
+     *
+     * ```text
+     *        lo-1
+     *            \
+     *             hi
+     *            /
+     *      [lo, hi)
+     * ```
+     */
+    pub fn reverse(&mut self, lo: usize, hi: usize) {
+        let n = self.len();
+        assert!(lo <= hi && hi <= n, "reverse: range {}..{} out of bounds for length {}", lo, hi, n);
+        if hi - lo < 2 {
+            return;
+        }
+
+        let r = match self.isolate(lo, hi) {
+            (Some(p), dir) => self.nodes[p].children[dir],
+            (None, _) => self.root
+        };
+        self.nodes[r.unwrap()].reversed ^= true;
+    }
+
+    /**
+     * Inserts values before position pos, so the first of them ends up at
+     * pos; pos = len() appends. The gap at pos is isolated as at reverse,
+     * where it is an empty subtree, and the values are hung there as a
+     * balanced subtree, in O(log n + m) amortized for m values.
+     */
+    pub fn insert_at(&mut self, pos: usize, values: Vec<T>) {
+        let n = self.len();
+        assert!(pos <= n, "insert_at: position {} out of bounds for length {}", pos, n);
+        if values.is_empty() {
+            return;
+        }
+
+        let (parent, dir) = self.isolate(pos, pos);
+        let start = self.nodes.len();
+        for value in values {
+            self.nodes.push(Node{ value: Some(value), parent: None, children: [None, None], size: 1, reversed: false });
+        }
+        let sub = self.build_balanced(start, self.nodes.len(), None);
+        match parent {
+            None => self.root = sub,
+            Some(p) => {
+                self.set_child(p, dir, sub);
+                self.update_sizes_up(p);
+            }
+        }
+    }
+
+    /**
+     * Removes the elements at positions lo..hi and returns them in order.
+     * The range is isolated as at reverse and cut off; its nodes are then
+     * taken out of the arena one at a time by swapping in the last node and
+     * relinking that, which keeps every node live, in O(log n + k) amortized
+     * for k elements.
+     */
+    pub fn remove_range(&mut self, lo: usize, hi: usize) -> Vec<T> {
+        let n = self.len();
+        assert!(lo <= hi && hi <= n, "remove_range: range {}..{} out of bounds for length {}", lo, hi, n);
+        if lo == hi {
+            return Vec::new();
+        }
+
+        let sub = match self.isolate(lo, hi) {
+            (None, _) => self.root.take(),
+            (Some(p), dir) => {
+                let sub = self.nodes[p].children[dir].take();
+                self.update_sizes_up(p);
+                sub
+            }
+        };
+        let order = self.inorder_from(sub);
+        let values = order.iter().map(|&i| self.nodes[i].value.take().unwrap()).collect();
+
+        let mut doomed = order;
+        doomed.sort_unstable_by(|a, b| b.cmp(a));
+        for i in doomed {
+            self.swap_remove_node(i);
+        }
+        values
+    }
+
+    // Copies of the elements at positions lo..hi, in order. The range is
+    // isolated as at reverse, so this splays like get does.
+    pub fn slice(&mut self, lo: usize, hi: usize) -> Vec<T> where T: Clone {
+        let n = self.len();
+        assert!(lo <= hi && hi <= n, "slice: range {}..{} out of bounds for length {}", lo, hi, n);
+        if lo == hi {
+            return Vec::new();
+        }
+        let sub = match self.isolate(lo, hi) {
+            (Some(p), dir) => self.nodes[p].children[dir],
+            (None, _) => self.root
+        };
+        self.inorder_from(sub).into_iter().map(|i| self.nodes[i].value.clone().unwrap()).collect()
+    }
+
+    /**
+     * Splits off the first k elements: returns (the first k, the rest). The
+     * element at position k is splayed to the root and its left link cut.
+     * Each half is moved into its own arena, which costs O(n).
+     */
+    pub fn split_at(mut self, k: usize) -> (Self, Self) {
+        if k >= self.len() {
+            let rest = self.take_subtree(None);
+            return (self, rest);
+        }
+        let idx = self.locate(k);
+        self.splay_to(idx, None);
+        let left = self.nodes[idx].children[LEFT].take();
+        self.update_size(idx);
+        (self.take_subtree(left), self.take_subtree(Some(idx)))
+    }
+
+    /**
+     * The elements of left followed by those of right. right's nodes are
+     * appended to left's arena; the last element of left is then splayed to
+     * the root, where it has no right child, and right is hung there.
+     */
+    pub fn concat(mut left: Self, right: Self) -> Self {
+        let offset = left.nodes.len();
+        let shift = |i: Option<usize>| i.map(|i| i + offset);
+        left.nodes.extend(right.nodes.into_iter().map(|node| Node{
+            value: node.value,
+            parent: shift(node.parent),
+            children: node.children.map(shift),
+            size: node.size,
+            reversed: node.reversed
+        }));
+        let b = shift(right.root);
+
+        let a = match left.root {
+            None => {
+                left.root = b;
+                return left;
+            },
+            Some(a) => a
+        };
+        let last = left.locate(left.subtree_size(Some(a)) - 1);
+        left.splay_to(last, None);
+        left.set_child(last, RIGHT, b);
+        left.update_size(last);
+        left
+    }
+
+    // The values in order, reading through any pending reversals without
+    // pushing them down
+    pub fn to_vec(&self) -> Vec<T> where T: Clone {
+        self.inorder_from(self.root).into_iter().map(|i| self.nodes[i].value.clone().unwrap()).collect()
+    }
+
+    // Indices of the nodes under sub in sequence order, reading through any
+    // pending reversals without pushing them down. sub's ancestors must have
+    // none pending.
+    fn inorder_from(&self, sub: Option<usize>) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.subtree_size(sub));
+        // (node, whether an odd number of reversals above it are pending, expanded yet)
+        let mut stack: Vec<(usize, bool, bool)> = sub.map(|r| (r, false, false)).into_iter().collect();
+        while let Some((i, flip, expanded)) = stack.pop() {
+            let node = &self.nodes[i];
+            if expanded {
+                order.push(i);
+                continue;
+            }
+            let flip = flip ^ node.reversed;
+            let [first, second] = if flip { [node.children[RIGHT], node.children[LEFT]] } else { node.children };
+            stack.extend(second.map(|c| (c, flip, false)));
+            stack.push((i, flip, true));
+            stack.extend(first.map(|c| (c, flip, false)));
+        }
+        order
+    }
+
+    /**
+     * Checks the tree structure: parent and child links agree, every node is
+     * reachable from the root exactly once, and every size is one more than
+     * its children's sizes combined.
+     */
+    pub fn check_invariants(&self) -> Result<(), String> {
+        let n = self.nodes.len();
+        let mut seen = vec![false; n];
+        let mut stack: Vec<(usize, Option<usize>)> = self.root.map(|r| (r, None)).into_iter().collect();
+        while let Some((i, parent)) = stack.pop() {
+            if i >= n || seen[i] {
+                return Err(format!("node {} is out of range or reachable twice", i));
+            }
+            seen[i] = true;
+            let node = &self.nodes[i];
+            if node.parent != parent {
+                return Err(format!("node {} has parent {:?} but is a child of {:?}", i, node.parent, parent));
+            }
+            let [l, r] = node.children;
+            let size = 1 + l.map_or(0, |l| self.nodes[l].size) + r.map_or(0, |r| self.nodes[r].size);
+            if node.size != size {
+                return Err(format!("node {} has size {} but its children's sizes add up to {}", i, node.size, size));
+            }
+            stack.extend(node.children.iter().flatten().map(|&c| (c, Some(i))));
+        }
+        match (0..n).find(|&i| !seen[i]) {
+            Some(i) => Err(format!("node {} is not reachable from the root", i)),
+            None => Ok(())
+        }
+    }
+
+    // Links nodes lo..hi into a balanced subtree under parent, returning its root
+    fn build_balanced(&mut self, lo: usize, hi: usize, parent: Option<usize>) -> Option<usize> {
+        if lo == hi {
+            return None;
+        }
+        let mid = lo + (hi - lo) / 2;
+        let left = self.build_balanced(lo, mid, Some(mid));
+        let right = self.build_balanced(mid + 1, hi, Some(mid));
+        let node = &mut self.nodes[mid];
+        node.parent = parent;
+        node.children = [left, right];
+        node.size = hi - lo;
+        Some(mid)
+    }
+
+    // Splays the elements just before and just after lo..hi so that the range
+    // is exactly one subtree, as drawn at reverse. Returns where that subtree
+    // hangs: its parent and side, or None for the parent if the range runs
+    // from the start to the end.
+    fn isolate(&mut self, lo: usize, hi: usize) -> (Option<usize>, usize) {
+        let n = self.len();
+        let before = if lo > 0 { Some(self.locate(lo - 1)) } else { None };
+        if let Some(a) = before {
+            self.splay_to(a, None);
+        }
+        let after = if hi < n { Some(self.locate(hi)) } else { None };
+        if let Some(b) = after {
+            self.splay_to(b, before);
+        }
+
+        match (before, after) {
+            (_, Some(b)) => (Some(b), LEFT),
+            (Some(a), None) => (Some(a), RIGHT),
+            (None, None) => (None, LEFT)
+        }
+    }
+
+    // Recomputes the sizes from idx up to the root, after a subtree below idx
+    // was added or cut off
+    fn update_sizes_up(&mut self, idx: usize) {
+        let mut cur = Some(idx);
+        while let Some(i) = cur {
+            self.update_size(i);
+            cur = self.nodes[i].parent;
+        }
+    }
+
+    // Drops the node at idx, already unlinked from the tree, from the arena
+    // by moving the last node into its slot and repointing that node's
+    // parent and children, or the root, at the new slot
+    fn swap_remove_node(&mut self, idx: usize) {
+        let last = self.nodes.len() - 1;
+        self.nodes.swap_remove(idx);
+        if idx == last {
+            return;
+        }
+        let Node { parent, children, .. } = self.nodes[idx];
+        match parent {
+            None => self.root = Some(idx),
+            Some(p) => {
+                let dir = if self.nodes[p].children[LEFT] == Some(last) { LEFT } else { RIGHT };
+                self.nodes[p].children[dir] = Some(idx);
+            }
+        }
+        for c in children.into_iter().flatten() {
+            self.nodes[c].parent = Some(idx);
+        }
+    }
+
+    // Moves the subtree rooted at sub into a new sequence with its own arena
+    fn take_subtree(&mut self, sub: Option<usize>) -> Self {
+        let mut seq = Self { root: None, nodes: Vec::new() };
+        let sub = match sub {
+            None => return seq,
+            Some(i) => i
+        };
+
+        // (old index, new parent index, side of the parent it hangs on) in preorder
+        let mut stack = vec![(sub, None, LEFT)];
+        while let Some((old, parent, side)) = stack.pop() {
+            let node = &mut self.nodes[old];
+            let new = seq.nodes.len();
+            seq.nodes.push(Node{ value: node.value.take(), parent, children: [None, None], size: node.size, reversed: node.reversed });
+
+            match parent {
+                None => seq.root = Some(new),
+                Some(p) => seq.nodes[p].children[side] = Some(new)
+            }
+            for dir in [RIGHT, LEFT] {
+                if let Some(c) = node.children[dir] {
+                    stack.push((c, Some(new), dir));
+                }
+            }
+        }
+        seq
+    }
+
+    // Applies a pending reversal at idx: swaps its children and passes the
+    // flag on to them. Must run before idx's children are read.
+    fn push_down(&mut self, idx: usize) {
+        let node = &mut self.nodes[idx];
+        if !node.reversed {
+            return;
+        }
+        node.reversed = false;
+        node.children.swap(LEFT, RIGHT);
+        for c in node.children.into_iter().flatten() {
+            self.nodes[c].reversed ^= true;
+        }
+    }
+
+    // Index of the node at position pos, pushing down every flag on the way
+    fn locate(&mut self, mut pos: usize) -> usize {
+        let mut cur = self.root.unwrap();
+        loop {
+            self.push_down(cur);
+            let node = &self.nodes[cur];
+            let below = self.subtree_size(node.children[LEFT]);
+            if pos < below {
+                cur = node.children[LEFT].unwrap();
+            }
+            else if pos == below {
+                return cur;
+            }
+            else {
+                pos -= below + 1;
+                cur = node.children[RIGHT].unwrap();
+            }
+        }
+    }
+
+    fn subtree_size(&self, idx: Option<usize>) -> usize {
+        idx.map_or(0, |i| self.nodes[i].size)
+    }
+
+    fn update_size(&mut self, idx: usize) {
+        let [l, r] = self.nodes[idx].children;
+        self.nodes[idx].size = 1 + self.subtree_size(l) + self.subtree_size(r);
+    }
+
+    fn set_child(&mut self, idx: usize, dir: usize, child: Option<usize>) {
+        self.nodes[idx].children[dir] = child;
+        if let Some(c) = child {
+            self.nodes[c].parent = Some(idx);
+        }
+    }
+
+    // Which side of its parent the node at idx hangs on
+    fn side(&self, idx: usize) -> usize {
+        let parent = &self.nodes[self.nodes[idx].parent.unwrap()];
+        if parent.children[LEFT] == Some(idx) { LEFT } else { RIGHT }
+    }
+
+    // Lifts y's child on side dir above y, as SplayTree::rotate does. Both
+    // nodes' flags are applied first, since a rotation reads their children.
+    fn rotate(&mut self, y_idx: usize, dir: usize) {
+        self.push_down(y_idx);
+        let x_idx = self.nodes[y_idx].children[dir].unwrap();
+        self.push_down(x_idx);
+        let z_idx = self.nodes[y_idx].parent;
+        let b_idx = self.nodes[x_idx].children[1 - dir];
+
+        self.set_child(y_idx, dir, b_idx);
+        self.set_child(x_idx, 1 - dir, Some(y_idx));
+        self.update_size(y_idx);
+        self.update_size(x_idx);
+
+        match z_idx {
+            None => {
+                self.root = Some(x_idx);
+                self.nodes[x_idx].parent = None;
+            },
+            Some(z) => {
+                let side = if self.nodes[z].children[LEFT] == Some(y_idx) { LEFT } else { RIGHT };
+                self.set_child(z, side, Some(x_idx));
+            }
+        }
+    }
+
+    // Splays x up until its parent is goal (None for the root). Any flags
+    // still pending on the path are pushed down from the top first: a flag
+    // above x decides which side x is on, so the steps can't be chosen
+    // until it has been applied.
+    fn splay_to(&mut self, x_idx: usize, goal: Option<usize>) {
+        let mut path = vec![x_idx];
+        while let Some(p) = self.nodes[*path.last().unwrap()].parent.filter(|&p| Some(p) != goal) {
+            path.push(p);
+        }
+        for &i in path.iter().rev() {
+            self.push_down(i);
+        }
+
+        while self.nodes[x_idx].parent != goal {
+            let y_idx = self.nodes[x_idx].parent.unwrap();
+            let x_dir = self.side(x_idx);
+            match self.nodes[y_idx].parent {
+                z if z == goal => self.rotate(y_idx, x_dir),    // zig
+                Some(z_idx) => {
+                    let y_dir = self.side(y_idx);
+                    if x_dir == y_dir {                         // zig-zig
+                        self.rotate(z_idx, y_dir);
+                        self.rotate(y_idx, x_dir);
+                    }
+                    else {                                      // zig-zag
+                        self.rotate(y_idx, x_dir);
+                        self.rotate(z_idx, y_dir);
+                    }
+                },
+                None => unreachable!("goal is not an ancestor")
+            }
+        }
+    }
+}
+
+impl<T> FromIterator<T> for SeqSplay<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_vec(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reverse_ranges() {
+        let mut seq: SeqSplay<usize> = (0..10).collect();
+        let mut model: Vec<usize> = (0..10).collect();
+        for (lo, hi) in [(2, 7), (0, 10), (5, 10), (0, 3), (4, 6), (3, 3), (1, 9)] {
+            seq.reverse(lo, hi);
+            model[lo..hi].reverse();
+            assert_eq!(seq.to_vec(), model);
+            assert_eq!(seq.check_invariants(), Ok(()));
+        }
+        for (pos, &v) in model.iter().enumerate() {
+            assert_eq!(seq.get(pos), Some(&v));
+        }
+        assert_eq!(seq.get(10), None);
+    }
+
+    #[test]
+    fn splay_pushes_pending_flags() {
+        // flag the subtree holding 0..7 by hand, then splay its deepest
+        // node without a locate first, so only the splay can push it down
+        let mut seq: SeqSplay<usize> = (0..15).collect();
+        let sub = seq.nodes[seq.root.unwrap()].children[LEFT].unwrap();
+        seq.nodes[sub].reversed = true;
+        let mut model: Vec<usize> = (0..15).collect();
+        model[..7].reverse();
+        assert_eq!(seq.to_vec(), model);
+
+        seq.splay_to(0, None);
+        assert_eq!(seq.root, Some(0));
+        assert!(!seq.nodes[sub].reversed);
+        assert_eq!(seq.to_vec(), model);
+        assert_eq!(seq.check_invariants(), Ok(()));
+        for (pos, &v) in model.iter().enumerate() {
+            assert_eq!(seq.get(pos), Some(&v));
+        }
+    }
+
+    #[test]
+    fn random_reverses_match_vec() {
+        let mut seed = 0x2545f4914f6cdd1d_u64;
+        let mut next = |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as usize % n
+        };
+        let mut seq: SeqSplay<usize> = (0..100).collect();
+        let mut model: Vec<usize> = (0..100).collect();
+        for _ in 0..500 {
+            let (a, b) = (next(101), next(101));
+            let (lo, hi) = (a.min(b), a.max(b));
+            seq.reverse(lo, hi);
+            model[lo..hi].reverse();
+            let pos = next(100);
+            assert_eq!(seq.get(pos), Some(&model[pos]));
+        }
+        assert_eq!(seq.to_vec(), model);
+        assert_eq!(seq.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn edits_with_pending_reversals_match_vec() {
+        let mut seed = 0x9e3779b97f4a7c15_u64;
+        let mut next = |n: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as usize % n
+        };
+        let mut seq: SeqSplay<usize> = (0..50).collect();
+        let mut model: Vec<usize> = (0..50).collect();
+        for round in 0..400 {
+            let (a, b) = (next(model.len() + 1), next(model.len() + 1));
+            let (lo, hi) = (a.min(b), a.max(b));
+            match next(4) {
+                0 => {
+                    seq.reverse(lo, hi);
+                    model[lo..hi].reverse();
+                },
+                1 => {
+                    let values: Vec<usize> = (0..next(4)).map(|i| 1000 + 10 * round + i).collect();
+                    seq.insert_at(lo, values.clone());
+                    model.splice(lo..lo, values);
+                },
+                2 => assert_eq!(seq.remove_range(lo, hi), model.drain(lo..hi).collect::<Vec<_>>()),
+                _ => assert_eq!(seq.slice(lo, hi), model[lo..hi])
+            }
+            assert_eq!(seq.len(), model.len());
+        }
+        assert_eq!(seq.to_vec(), model);
+        assert_eq!(seq.check_invariants(), Ok(()));
+
+        assert_eq!(seq.remove_range(0, model.len()), model);
+        assert!(seq.is_empty());
+        seq.insert_at(0, vec![7, 8]);
+        assert_eq!(seq.to_vec(), vec![7, 8]);
+        assert_eq!(seq.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn split_and_concat() {
+        let mut seq: SeqSplay<char> = "splaytree".chars().collect();
+        seq.reverse(0, 5);
+        let (left, right) = seq.split_at(3);
+        assert_eq!(left.to_vec(), vec!['y', 'a', 'l']);
+        assert_eq!(right.to_vec(), "pstree".chars().collect::<Vec<_>>());
+        assert_eq!(left.check_invariants(), Ok(()));
+        assert_eq!(right.check_invariants(), Ok(()));
+
+        let mut both = SeqSplay::concat(right, left);
+        assert_eq!(both.to_vec(), "pstreeyal".chars().collect::<Vec<_>>());
+        assert_eq!(both.check_invariants(), Ok(()));
+        *both.get_mut(0).unwrap() = 'P';
+        assert_eq!(both.get(0), Some(&'P'));
+
+        let (all, none) = both.split_at(20);
+        assert_eq!((all.len(), none.len()), (9, 0));
+        let (none, all) = all.split_at(0);
+        assert!(none.is_empty());
+        assert_eq!(SeqSplay::concat(none, all).len(), 9);
+    }
+}
@@ -0,0 +1,81 @@
+/*
+* serialize.rs
+*
+* Serde support, behind the serde feature. A tree is written as its root and
+* node arena, so the exact shape survives a round trip: a tree saved mid-way
+* through a sequence of splays can be reloaded in the same state. The free
+* list and len are rebuilt from the arena when reading it back.
+*/
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Natural, Node, NodeId, SplayTree};
+
+#[derive(Serialize)]
+struct TreeRef<'a, K, V> {
+    root: Option<NodeId>,
+    nodes: &'a [Node<K, V>]
+}
+
+#[derive(Deserialize)]
+struct TreeData<K, V> {
+    root: Option<NodeId>,
+    nodes: Vec<Node<K, V>>
+}
+
+// The comparator is not written out, so a tree with_comparator can be saved
+// but only a tree in its keys' own order read back
+impl<K: Serialize, V: Serialize, C> Serialize for SplayTree<K, V, C> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TreeRef { root: self.root, nodes: &self.nodes }.serialize(serializer)
+    }
+}
+
+// Input is checked with check_invariants, so corrupt links, keys out of
+// order, or wrong sizes are an error rather than a tree that misbehaves later.
+impl<'de, K: Ord + Deserialize<'de>, V: Deserialize<'de>> Deserialize<'de> for SplayTree<K, V> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let TreeData { root, nodes } = TreeData::deserialize(deserializer)?;
+        let free: Vec<NodeId> = (0..nodes.len()).rev().map(NodeId).filter(|&i| nodes[i].entry.is_none()).collect();
+        let tree = SplayTree { root, len: nodes.len() - free.len(), nodes, free, rotations: 0, splay_steps: 0, cmp: Natural };
+        tree.check_invariants().map_err(D::Error::custom)?;
+        Ok(tree)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{NodeId, SplayTree};
+
+    #[test]
+    fn round_trip_keeps_shape() {
+        let mut tree = SplayTree::from_sorted_pairs((0..10).map(|i| (i, i.to_string())).collect());
+        tree.splay(Some(NodeId(3)));
+        tree.remove(&6);
+        tree.splay(Some(NodeId(8)));
+
+        let json = serde_json::to_string(&tree).unwrap();
+        let mut back: SplayTree<i32, String> = serde_json::from_str(&json).unwrap();
+        assert_eq!(format!("{:?}", back), format!("{:?}", tree));
+        assert_eq!(back.len(), 9);
+
+        // the removed slot is free again
+        assert_eq!(back.insert_pair(6, "six".to_string()), NodeId(6));
+        assert_eq!(back.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn corrupt_input_is_an_error() {
+        let tree = SplayTree::new(3);
+        let json = serde_json::to_string(&tree).unwrap();
+        assert!(serde_json::from_str::<SplayTree<usize>>(&json).is_ok());
+
+        let bad = json.replacen("\"size\":3", "\"size\":4", 1);
+        let err = serde_json::from_str::<SplayTree<usize>>(&bad).unwrap_err();
+        assert!(err.to_string().contains("node 2 has size 4"), "{}", err);
+
+        let bad = json.replacen("\"root\":2", "\"root\":1", 1);
+        assert!(serde_json::from_str::<SplayTree<usize>>(&bad).is_err());
+    }
+}
@@ -0,0 +1,1443 @@
+use std::collections::BTreeSet;
+use std::ops::Bound;
+
+use splay_tree::{Entry, NodeId, SplayError, SplayTree};
+
+// Handle for index i; in new(n) and from_sorted_*, the key at position i is at index i
+fn id(i: usize) -> NodeId {
+    NodeId::from_index(i)
+}
+
+#[test]
+fn public_api_builds_and_splays() {
+    let mut tree = SplayTree::new(10);
+    for i in 0..10 {
+        assert_eq!(tree.splay(Some(id(i))), id(i));
+    }
+}
+
+#[test]
+fn len_and_is_empty() {
+    let tree = SplayTree::new(10);
+    assert_eq!(tree.len(), 10);
+    assert!(!tree.is_empty());
+
+    let empty = SplayTree::new(0);
+    assert_eq!(empty.len(), 0);
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn debug_lists_every_node() {
+    let mut tree = SplayTree::new(3);
+    assert_eq!(tree.splay(Some(id(0))), id(0));
+
+    let expected = "root: 0 \nnodes:\n\
+                    index: 0, key: 0, value: (), parent: none, left: none, right: 1\n\
+                    index: 1, key: 1, value: (), parent: 0, left: none, right: 2\n\
+                    index: 2, key: 2, value: (), parent: 1, left: none, right: none\n";
+    assert_eq!(format!("{:?}", tree), expected);
+    assert_eq!(format!("{:?}", SplayTree::new(0)), "root: none \nnodes:\n");
+}
+
+#[test]
+fn display_draws_shape_after_splay() {
+    let mut tree = SplayTree::new(5);
+    tree.splay(Some(id(2)));
+
+    let expected = "2\n  \
+                      L: 1\n    \
+                        L: 0\n  \
+                      R: 3\n    \
+                        R: 4\n";
+    assert_eq!(tree.to_string(), expected);
+    assert_eq!(SplayTree::new(0).to_string(), "(empty)\n");
+}
+
+#[test]
+fn try_splay_checks_bounds() {
+    let mut tree = SplayTree::new(10);
+    assert_eq!(tree.try_splay(id(999)), Err(SplayError::OutOfRange { index: id(999), len: 10 }));
+    assert_eq!(tree.try_splay(id(4)), Ok(id(4)));
+
+    let mut empty = SplayTree::new(0);
+    assert_eq!(empty.try_splay(id(0)), Err(SplayError::OutOfRange { index: id(0), len: 0 }));
+}
+
+#[test]
+#[should_panic(expected = "node index 999 out of range for tree of 10 nodes")]
+fn splay_panics_out_of_range() {
+    SplayTree::new(10).splay(Some(id(999)));
+}
+
+#[test]
+fn str_keys() {
+    let mut tree = SplayTree::from_sorted_keys(vec!["apple", "banana", "cherry", "date"]);
+    assert_eq!(tree.len(), 4);
+    assert_eq!(*tree.key(id(1)), "banana");
+
+    let root = tree.splay(Some(id(1)));
+    assert_eq!(*tree.key(root), "banana");
+    assert_eq!(tree.to_string(), "banana\n  L: apple\n  R: cherry\n    R: date\n");
+}
+
+#[test]
+fn values_survive_splay() {
+    let pairs = vec![(1u32, "one".to_string()), (2, "two".to_string()), (3, "three".to_string())];
+    let mut tree = SplayTree::from_sorted_pairs(pairs);
+
+    let root = tree.splay(Some(id(0)));
+    assert_eq!(tree.value(root), "one");
+    tree.value_mut(root).push_str("!!");
+
+    tree.splay(Some(id(2)));
+    assert_eq!(*tree.key(id(0)), 1);
+    assert_eq!(tree.value(id(0)), "one!!");
+    assert_eq!(tree.value(id(1)), "two");
+    assert_eq!(tree.value(id(2)), "three");
+}
+
+#[test]
+fn get_does_not_splay() {
+    let mut tree = SplayTree::from_sorted_pairs((0..10).map(|i| (i, i * i)).collect());
+    tree.splay(Some(id(6)));
+    let dump = format!("{:?}", tree);
+    assert_eq!(tree.get(&3), Some(&9));
+    assert_eq!(tree.get(&0), Some(&0));
+    assert_eq!(tree.get(&10), None);
+    assert_eq!(format!("{:?}", tree), dump);
+
+    *tree.get_mut(&2).unwrap() = 5;
+    assert_eq!(tree.get_mut(&-1), None);
+    assert_eq!(tree.depth(id(6)), 0);
+    assert_eq!(tree.value(id(2)), &5);
+    assert_eq!(SplayTree::<i32, i32>::from_sorted_pairs(vec![]).get(&1), None);
+}
+
+#[test]
+fn contains_present_and_absent() {
+    let mut tree = SplayTree::from_sorted_keys(vec![2, 4, 6, 8]);
+    assert!(tree.contains(&6));
+    assert!(!tree.contains(&5));
+    assert!(!tree.contains(&100));
+    assert_eq!(tree.len(), 4);
+}
+
+#[test]
+fn insert_into_empty_tree() {
+    let mut tree = SplayTree::from_sorted_keys(Vec::new());
+    for k in [5, 1, 9, 3, 7] {
+        let idx = tree.insert(k);
+        assert_eq!(*tree.key(idx), k);
+    }
+    assert_eq!(tree.len(), 5);
+    assert_eq!(tree.to_string(), "7\n  L: 5\n    L: 3\n      L: 1\n  R: 9\n");
+}
+
+#[test]
+fn insert_interleaved_with_find() {
+    let mut tree = SplayTree::new(5);
+    let idx = tree.insert(10);
+    assert_eq!(tree.find(&10), Some(idx));
+    assert_eq!(tree.find(&7), None);
+
+    // duplicate keys keep their node
+    assert_eq!(tree.insert(3), id(3));
+    assert_eq!(tree.len(), 6);
+
+    let mut map = SplayTree::from_sorted_pairs(vec![(1, "a"), (2, "b")]);
+    let idx = map.insert_pair(2, "B");
+    assert_eq!(idx, id(1));
+    assert_eq!(*map.value(idx), "B");
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn splay_key_hit_and_miss() {
+    let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40, 50]);
+    let idx = tree.splay_key(&20).unwrap();
+    assert_eq!(*tree.key(idx), 20);
+    assert_eq!(tree.depth(idx), 0);
+
+    // a miss splays the last node on the search path
+    assert_eq!(tree.search_path(&35).last().map(|&i| *tree.key(i)), Some(40));
+    assert_eq!(tree.splay_key(&35), None);
+    assert_eq!(tree.depth(id(3)), 0);
+    assert_eq!(tree.splay_key(&5), None);
+    assert_eq!(tree.depth(id(0)), 0);
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(SplayTree::new(0).splay_key(&1), None);
+}
+
+#[test]
+fn rank_counts_smaller_keys() {
+    let mut tree = SplayTree::new(10);
+    for i in [4, 0, 9, 7, 2] {
+        assert_eq!(tree.rank(&i), i);
+    }
+
+    let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+    assert_eq!(tree.rank(&5), 0);
+    assert_eq!(tree.rank(&25), 2);
+    assert_eq!(tree.rank(&40), 3);
+    assert_eq!(tree.rank(&45), 4);
+    assert_eq!(SplayTree::new(0).rank(&3), 0);
+}
+
+#[test]
+fn select_every_rank() {
+    let mut tree = SplayTree::from_sorted_keys((0..10).map(|i| i * 3).collect());
+    tree.splay(Some(id(6)));
+    for k in [0, 9, 4, 5, 1, 8, 2, 7, 3, 6] {
+        let idx = tree.select(k).unwrap();
+        assert_eq!(*tree.key(idx), 3 * k);
+        assert_eq!(tree.rank(&(3 * k)), k);
+    }
+    assert_eq!(tree.select(10), None);
+    assert_eq!(SplayTree::new(0).select(0), None);
+}
+
+#[test]
+fn min_and_max_splay_the_extremes() {
+    let mut tree = SplayTree::new(10);
+    assert_eq!(tree.min(), Some(id(0)));
+    assert_eq!(tree.depth(id(0)), 0);
+    assert_eq!(tree.max(), Some(id(9)));
+    assert_eq!(tree.depth(id(9)), 0);
+    assert_eq!(tree.min(), Some(id(0)));
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    let mut empty = SplayTree::new(0);
+    assert_eq!(empty.min(), None);
+    assert_eq!(empty.max(), None);
+}
+
+#[test]
+fn first_and_last_do_not_splay() {
+    let mut tree = SplayTree::new(10);
+    tree.splay(Some(id(4)));
+    let dump = format!("{:?}", tree);
+    assert_eq!(tree.first(), Some(id(0)));
+    assert_eq!(tree.last(), Some(id(9)));
+    assert_eq!(format!("{:?}", tree), dump);
+    assert_eq!(tree.depth(id(4)), 0);
+
+    assert_eq!(SplayTree::new(0).first(), None);
+    assert_eq!(SplayTree::new(0).last(), None);
+}
+
+#[test]
+fn pop_min_and_pop_max_drain_in_order() {
+    let mut seed = 0x9e3779b97f4a7c15_u64;
+    let mut keys = Vec::new();
+    for _ in 0..200 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        keys.push(seed % 1000);
+    }
+    let mut tree: SplayTree<u64> = keys.iter().copied().collect();
+    let mut sorted = keys.clone();
+    sorted.sort();
+    sorted.dedup();
+
+    let mut up = tree.clone();
+    let ascending: Vec<u64> = std::iter::from_fn(|| up.pop_min()).collect();
+    assert_eq!(ascending, sorted);
+    assert!(up.is_empty());
+
+    let descending: Vec<u64> = std::iter::from_fn(|| tree.pop_max()).collect();
+    sorted.reverse();
+    assert_eq!(descending, sorted);
+    assert_eq!(tree.pop_max(), None);
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn retain_even_keys() {
+    let mut tree = SplayTree::new(10);
+    tree.splay(Some(id(3)));
+    let mut seen = Vec::new();
+    tree.retain(|&k| {
+        seen.push(k);
+        k % 2 == 0
+    });
+    assert_eq!(seen, (0..10).collect::<Vec<_>>());
+    assert_eq!(tree.len(), 5);
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(*tree.key(id(8)), 8);
+    assert!(!tree.contains(&3));
+
+    // freed slots are reused
+    assert!(tree.insert(11).index() % 2 == 1);
+    tree.retain(|_| false);
+    assert!(tree.is_empty());
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(tree.into_keys().count(), 0);
+}
+
+#[test]
+fn extend_inserts_each_key() {
+    let mut tree: SplayTree<i32> = vec![5, 1, 3].into_iter().collect();
+    let idx = tree.find(&3).unwrap();
+    tree.extend(vec![4, 3, 9, 1, 0]);
+    assert_eq!(tree.len(), 6);
+    assert_eq!(tree.find(&3), Some(idx));
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(tree.into_keys().collect::<Vec<_>>(), vec![0, 1, 3, 4, 5, 9]);
+}
+
+#[test]
+fn split_off_keeps_smaller_keys() {
+    let keys = |tree: SplayTree<i32>| tree.into_keys().collect::<Vec<_>>();
+    for (at, low, high) in [(30, vec![10, 20], vec![30, 40]), (25, vec![10, 20], vec![30, 40]),
+                            (5, vec![], vec![10, 20, 30, 40]), (45, vec![10, 20, 30, 40], vec![])] {
+        let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+        tree.find(&20);
+        let upper = tree.split_off(&at);
+        assert_eq!(tree.check_invariants(), Ok(()));
+        assert_eq!(upper.check_invariants(), Ok(()));
+        assert_eq!(keys(tree), low);
+        assert_eq!(keys(upper), high);
+    }
+
+    let mut empty = SplayTree::new(0);
+    assert!(empty.split_off(&3).is_empty());
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn append_disjoint_and_overlapping() {
+    let pairs = |tree: SplayTree<i32, char>| tree.into_iter().collect::<Vec<_>>();
+
+    // disjoint, in either order
+    let mut low = SplayTree::from_sorted_pairs(vec![(1, 'a'), (2, 'b')]);
+    low.append(SplayTree::from_sorted_pairs(vec![(5, 'e'), (6, 'f')]));
+    assert_eq!(low.check_invariants(), Ok(()));
+    let mut high = SplayTree::from_sorted_pairs(vec![(8, 'h')]);
+    high.append(low);
+    assert_eq!(high.check_invariants(), Ok(()));
+    assert_eq!(pairs(high), vec![(1, 'a'), (2, 'b'), (5, 'e'), (6, 'f'), (8, 'h')]);
+
+    // overlapping, other's values win
+    let mut tree = SplayTree::from_sorted_pairs(vec![(1, 'a'), (3, 'c'), (5, 'e')]);
+    let idx = tree.find(&3).unwrap();
+    tree.append(SplayTree::from_sorted_pairs(vec![(2, 'B'), (3, 'C'), (6, 'F')]));
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(*tree.value(idx), 'C');
+    assert_eq!(pairs(tree), vec![(1, 'a'), (2, 'B'), (3, 'C'), (5, 'e'), (6, 'F')]);
+
+    let mut empty = SplayTree::from_sorted_pairs(vec![]);
+    empty.append(SplayTree::from_sorted_pairs(vec![(4, 'd')]));
+    empty.append(SplayTree::from_sorted_pairs(vec![]));
+    assert_eq!(pairs(empty), vec![(4, 'd')]);
+}
+
+#[test]
+fn union_of_overlapping_sets() {
+    let evens: SplayTree<i32> = (0..40).step_by(2).collect();
+    let threes: SplayTree<i32> = (0..60).step_by(3).collect();
+    let union = evens.union(threes);
+    assert_eq!(union.check_invariants(), Ok(()));
+    let expected: Vec<i32> = (0..60).filter(|k| (k % 2 == 0 && *k < 40) || k % 3 == 0).collect();
+    assert_eq!(union.len(), expected.len());
+    assert_eq!(union.into_keys().collect::<Vec<_>>(), expected);
+
+    // other's values win, whichever tree is smaller
+    let pairs = |tree: SplayTree<i32, char>| tree.into_iter().collect::<Vec<_>>();
+    let small = SplayTree::from_sorted_pairs(vec![(2, 'b'), (4, 'd')]);
+    let large = SplayTree::from_sorted_pairs(vec![(1, 'A'), (2, 'B'), (3, 'C'), (4, 'D'), (5, 'E')]);
+    assert_eq!(pairs(small.clone().union(large.clone())), vec![(1, 'A'), (2, 'B'), (3, 'C'), (4, 'D'), (5, 'E')]);
+    assert_eq!(pairs(large.union(small)), vec![(1, 'A'), (2, 'b'), (3, 'C'), (4, 'd'), (5, 'E')]);
+
+    let empty: SplayTree<i32> = SplayTree::from_sorted_keys(vec![]);
+    let keys = SplayTree::from_sorted_keys(vec![1, 2, 3]);
+    assert_eq!(empty.clone().union(keys.clone()).into_keys().collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(keys.union(empty).into_keys().collect::<Vec<_>>(), vec![1, 2, 3]);
+}
+
+#[test]
+fn intersection_and_difference_match_btreeset() {
+    let sets: [Vec<u32>; 4] = [vec![], vec![1, 4, 9], (0..30).step_by(3).collect(), (5..25).collect()];
+    for a in &sets {
+        for b in &sets {
+            let (ta, tb): (SplayTree<u32>, SplayTree<u32>) = (a.iter().copied().collect(), b.iter().copied().collect());
+            let (sa, sb): (BTreeSet<u32>, BTreeSet<u32>) = (a.iter().copied().collect(), b.iter().copied().collect());
+
+            let both = ta.intersection(&tb);
+            assert_eq!(both.check_invariants(), Ok(()));
+            assert_eq!(both.into_keys().collect::<Vec<_>>(), sa.intersection(&sb).copied().collect::<Vec<_>>());
+            let only = ta.difference(&tb);
+            assert_eq!(only.check_invariants(), Ok(()));
+            assert_eq!(only.into_keys().collect::<Vec<_>>(), sa.difference(&sb).copied().collect::<Vec<_>>());
+        }
+    }
+
+    let ours = SplayTree::from_sorted_pairs(vec![(1, 'a'), (2, 'b'), (3, 'c')]);
+    let theirs = SplayTree::from_sorted_pairs(vec![(2, 'B'), (3, 'C'), (4, 'D')]);
+    assert_eq!(ours.intersection(&theirs).into_iter().collect::<Vec<_>>(), vec![(2, 'b'), (3, 'c')]);
+    assert_eq!(ours.difference(&theirs).into_iter().collect::<Vec<_>>(), vec![(1, 'a')]);
+}
+
+#[test]
+fn entry_inserts_or_modifies() {
+    let mut counts: SplayTree<&str, u32> = SplayTree::from_sorted_pairs(vec![]);
+    for word in ["b", "a", "b", "c", "b", "a"] {
+        *counts.entry(word).or_insert(0) += 1;
+    }
+    assert_eq!(counts.into_iter().collect::<Vec<_>>(), vec![("a", 2), ("b", 3), ("c", 1)]);
+
+    let mut tree = SplayTree::from_sorted_pairs(vec![(1, 10), (2, 20), (3, 30)]);
+    tree.entry(2).and_modify(|v| *v += 1).or_insert(0);
+    tree.entry(4).and_modify(|v| *v += 1).or_insert(40);
+    assert_eq!(*tree.entry(5).or_default(), 0);
+    match tree.entry(1) {
+        Entry::Occupied(mut e) => {
+            assert_eq!(e.insert(11), 10);
+            assert_eq!(e.index(), id(0));
+        },
+        Entry::Vacant(_) => panic!("1 is in the tree")
+    }
+    match tree.entry(3) {
+        Entry::Occupied(e) => assert_eq!(e.remove(), 30),
+        Entry::Vacant(_) => panic!("3 is in the tree")
+    }
+    match tree.entry(3) {
+        Entry::Occupied(_) => panic!("3 was removed"),
+        Entry::Vacant(e) => assert_eq!(e.into_key(), 3)
+    }
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(tree.into_iter().collect::<Vec<_>>(), vec![(1, 11), (2, 21), (4, 40), (5, 0)]);
+}
+
+#[test]
+fn get_or_insert_with_computes_once() {
+    let mut memo: SplayTree<u64, u64> = SplayTree::from_sorted_pairs(vec![]);
+    let mut calls = 0;
+    for _ in 0..3 {
+        let v = memo.get_or_insert_with(10, || {
+            calls += 1;
+            55
+        });
+        assert_eq!(*v, 55);
+    }
+    assert_eq!(calls, 1);
+
+    *memo.get_or_insert_with(10, || unreachable!()) = 89;
+    assert_eq!(*memo.get_or_insert_with(11, || 1), 1);
+    assert_eq!(memo.into_iter().collect::<Vec<_>>(), vec![(10, 89), (11, 1)]);
+}
+
+#[test]
+fn counters_track_splay_work() {
+    // 0 is at depth 9 on the path: four zig-zigs and a zig
+    let mut tree = SplayTree::new(10);
+    tree.splay(Some(id(0)));
+    assert_eq!(tree.rotation_count(), 9);
+    assert_eq!(tree.splay_step_count(), 5);
+
+    tree.splay(Some(id(0)));
+    assert_eq!(tree.rotation_count(), 9);
+    tree.reset_counters();
+    assert_eq!((tree.rotation_count(), tree.splay_step_count()), (0, 0));
+
+    // a zig-zig, then a zig
+    let mut tree = SplayTree::new(3);
+    tree.splay(Some(id(0)));
+    assert_eq!((tree.rotation_count(), tree.splay_step_count()), (2, 1));
+    tree.splay(Some(id(1)));
+    assert_eq!((tree.rotation_count(), tree.splay_step_count()), (3, 2));
+}
+
+#[test]
+fn skewed_accesses_stay_within_working_set_bound() {
+    // mostly a handful of hot keys, with an occasional cold one
+    let mut seed = 0x2545f4914f6cdd1d_u64;
+    let mut accesses = Vec::new();
+    for j in 0..2000 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        accesses.push(if j % 10 == 0 { (seed % 500) as usize } else { (seed % 5) as usize });
+    }
+    let mut tree = SplayTree::new(500);
+    for k in &accesses {
+        tree.find(k);
+    }
+    assert!(tree.within_working_set_bound(&accesses));
+    assert!(tree.working_set_bound(&accesses) < 2000.0 * 3.0 * 1.0f64.mul_add(2.0 * 500f64.log2(), 1.0));
+
+    // repeating one key costs almost nothing beyond the potential term
+    let n = 500f64;
+    assert_eq!(tree.working_set_bound(&[7, 7, 7]), n * (1.0 + 2.0 * n.log2()) + (3.0 * (1.0 + 2.0 * n.log2()) + 1.0) + 8.0);
+
+    // counters that include accesses outside the sequence can break the bound
+    let mut small = SplayTree::new(2);
+    for _ in 0..10 {
+        small.splay(Some(id(0)));
+        small.splay(Some(id(1)));
+    }
+    assert!(!small.within_working_set_bound(&[]));
+}
+
+#[test]
+fn bulk_splay_counts_rotations() {
+    let mut tree = SplayTree::new(10);
+    let mut single = tree.clone();
+    let seq = [0, 5, 2, 8].map(id);
+    let rotations = tree.bulk_splay(&seq);
+    for &i in &seq {
+        single.splay(Some(i));
+    }
+    assert_eq!(rotations, single.rotation_count());
+    assert_eq!(format!("{:?}", tree), format!("{:?}", single));
+
+    // once a key is at the root, repeating it is free
+    assert!(tree.bulk_splay(&[id(3)]) > 0);
+    assert_eq!(tree.bulk_splay(&[id(3); 3]), 0);
+    assert_eq!(tree.bulk_splay(&[]), 0);
+}
+
+#[test]
+fn equality_ignores_shape() {
+    let mut seed = 0x9e3779b97f4a7c15_u64;
+    let mut shuffled = SplayTree::from_sorted_keys(vec![]);
+    while shuffled.len() < 50 {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        shuffled.insert((seed % 50) as usize);
+    }
+    let mut path = SplayTree::new(50);
+    assert_ne!(format!("{:?}", path), format!("{:?}", shuffled));
+    assert_eq!(path, shuffled);
+    path.splay(Some(id(20)));
+    assert_eq!(path, shuffled);
+
+    shuffled.remove(&7);
+    assert_ne!(path, shuffled);
+    shuffled.insert(7);
+    assert_eq!(path, shuffled);
+
+    let a = SplayTree::from_sorted_pairs(vec![(1, 'a'), (2, 'b')]);
+    let b = SplayTree::from_sorted_pairs(vec![(1, 'a'), (2, 'B')]);
+    assert_ne!(a, b);
+    assert_eq!(SplayTree::<i32>::default(), SplayTree::from_sorted_keys(vec![]));
+}
+
+#[test]
+fn default_is_empty() {
+    #[derive(Default)]
+    struct Index {
+        words: SplayTree<String, usize>
+    }
+    let mut index = Index::default();
+    assert!(index.words.is_empty());
+    assert_eq!(index.words.check_invariants(), Ok(()));
+    index.words.insert_pair("splay".to_string(), 1);
+    assert_eq!(index.words.len(), 1);
+    assert!(SplayTree::<i32>::default().is_empty());
+}
+
+#[test]
+fn with_capacity_does_not_reallocate() {
+    let mut tree = SplayTree::with_capacity(100);
+    assert!(tree.is_empty());
+    let cap = tree.capacity();
+    assert!(cap >= 100);
+    for k in 0..100 {
+        tree.insert((k * 37) % 100);
+    }
+    assert_eq!(tree.len(), 100);
+    assert_eq!(tree.capacity(), cap);
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn reserve_grows_capacity() {
+    let mut tree = SplayTree::new(10);
+    tree.reserve(50);
+    let cap = tree.capacity();
+    assert!(cap >= 10 + 50, "capacity {}", cap);
+    for k in 10..60 {
+        tree.insert(k);
+    }
+    assert_eq!(tree.capacity(), cap);
+
+    // already enough room: nothing changes
+    tree.reserve(cap - 60);
+    assert_eq!(tree.capacity(), cap);
+}
+
+#[test]
+fn clear_and_reset_to_path() {
+    let mut tree = SplayTree::new(10);
+    tree.splay(Some(id(3)));
+    tree.remove(&6);
+    let bytes = tree.memory_bytes();
+
+    tree.reset_to_path(8);
+    assert_eq!(format!("{:?}", tree), format!("{:?}", SplayTree::new(8)));
+    assert_eq!(tree.rotation_count(), 0);
+    assert_eq!(tree.memory_bytes(), bytes);
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    tree.clear();
+    assert!(tree.is_empty());
+    assert_eq!(format!("{:?}", tree), format!("{:?}", SplayTree::new(0)));
+    assert_eq!(tree.insert(4), id(0));
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn most_accessed_counts_splays() {
+    let mut tree = SplayTree::new(10);
+    assert_eq!(tree.most_accessed(), Some(id(0)));
+    for (idx, times) in [(id(3), 2), (id(7), 4), (id(5), 3)] {
+        for _ in 0..times {
+            tree.splay(Some(idx));
+        }
+    }
+    assert_eq!(tree.most_accessed(), Some(id(7)));
+
+    // find splays its target too
+    for _ in 0..2 {
+        tree.find(&5);
+    }
+    assert_eq!(tree.most_accessed(), Some(id(5)));
+
+    tree.reset_accesses();
+    tree.splay(Some(id(9)));
+    assert_eq!(tree.most_accessed(), Some(id(9)));
+    assert_eq!(SplayTree::new(0).most_accessed(), None);
+}
+
+#[test]
+fn predecessor_and_successor() {
+    let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+    for (key, pred, succ) in [(25, Some(20), Some(30)), (30, Some(20), Some(40)), (20, Some(10), Some(30))] {
+        let idx = tree.predecessor(&key).unwrap();
+        assert_eq!(tree.key(idx), &pred.unwrap());
+        assert_eq!(tree.depth(idx), 0);
+        let idx = tree.successor(&key).unwrap();
+        assert_eq!(tree.key(idx), &succ.unwrap());
+        assert_eq!(tree.depth(idx), 0);
+    }
+
+    // boundaries: nothing below the minimum or above the maximum
+    assert_eq!(tree.predecessor(&10), None);
+    assert_eq!(tree.predecessor(&5), None);
+    assert_eq!(tree.successor(&40), None);
+    assert_eq!(tree.successor(&45), None);
+    assert_eq!(tree.predecessor(&45).map(|i| *tree.key(i)), Some(40));
+    assert_eq!(tree.successor(&5).map(|i| *tree.key(i)), Some(10));
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    assert_eq!(SplayTree::new(0).predecessor(&3), None);
+    assert_eq!(SplayTree::new(0).successor(&3), None);
+}
+
+#[test]
+fn lower_and_upper_bound() {
+    let mut tree = SplayTree::new(10);
+    for (key, lower, upper) in [(4, Some(4), Some(5)), (0, Some(0), Some(1)), (8, Some(8), Some(9)), (9, Some(9), None), (10, None, None)] {
+        assert_eq!(tree.lower_bound(&key), lower.map(id), "lower_bound({})", key);
+        if let Some(i) = lower {
+            assert_eq!(tree.depth(id(i)), 0);
+        }
+        assert_eq!(tree.upper_bound(&key), upper.map(id), "upper_bound({})", key);
+        if let Some(i) = upper {
+            assert_eq!(tree.depth(id(i)), 0);
+        }
+    }
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    // between keys, the two agree
+    let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30]);
+    for (key, bound) in [(5, Some(10)), (15, Some(20)), (25, Some(30)), (35, None)] {
+        assert_eq!(tree.lower_bound(&key).map(|i| *tree.key(i)), bound);
+        assert_eq!(tree.upper_bound(&key).map(|i| *tree.key(i)), bound);
+    }
+
+    assert_eq!(SplayTree::new(0).lower_bound(&3), None);
+    assert_eq!(SplayTree::new(0).upper_bound(&3), None);
+}
+
+#[test]
+fn floor_and_ceiling() {
+    let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+    for (key, floor, ceiling) in [(25, Some(20), Some(30)), (5, None, Some(10)), (45, Some(40), None)] {
+        assert_eq!(tree.floor(&key).map(|i| *tree.key(i)), floor, "floor({})", key);
+        assert_eq!(tree.ceiling(&key).map(|i| *tree.key(i)), ceiling, "ceiling({})", key);
+    }
+
+    // an exact match is its own floor and ceiling, where predecessor and
+    // successor step past it
+    for key in [10, 20, 30, 40] {
+        let idx = tree.floor(&key).unwrap();
+        assert_eq!((tree.key(idx), tree.depth(idx)), (&key, 0));
+        let idx = tree.ceiling(&key).unwrap();
+        assert_eq!((tree.key(idx), tree.depth(idx)), (&key, 0));
+        assert_eq!(tree.ceiling(&key), tree.lower_bound(&key));
+    }
+    assert_eq!(tree.predecessor(&20).map(|i| *tree.key(i)), Some(10));
+    assert_eq!(tree.successor(&20).map(|i| *tree.key(i)), Some(30));
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    assert_eq!(SplayTree::new(0).floor(&3), None);
+    assert_eq!(SplayTree::new(0).ceiling(&3), None);
+}
+
+#[test]
+fn search_path_follows_depth() {
+    let mut tree = SplayTree::new(10);
+    tree.splay(Some(id(4)));
+    let dump = format!("{:?}", tree);
+    for k in 0..10 {
+        let path = tree.search_path(&k);
+        assert_eq!(path.len(), tree.depth(id(k)) + 1);
+        assert_eq!(path[0], id(4));
+        assert_eq!(*path.last().unwrap(), id(k));
+    }
+    assert_eq!(format!("{:?}", tree), dump);
+
+    // a miss stops at the node find would splay
+    let mut words = SplayTree::from_sorted_keys(vec!["b", "d", "f"]);
+    assert_eq!(words.search_path(&"c"), [2, 1, 0].map(id));
+    words.find(&"c");
+    assert_eq!(words.search_path(&"c"), [0, 1].map(id));
+    assert!(SplayTree::new(0).search_path(&3).is_empty());
+}
+
+#[test]
+fn range_over_bounds() {
+    // keys equal indices in new(n)
+    let mut tree = SplayTree::new(20);
+    tree.splay(Some(id(7)));
+    assert_eq!(tree.range(3..8).map(NodeId::index).collect::<Vec<_>>(), vec![3, 4, 5, 6, 7]);
+    assert_eq!(tree.depth(id(3)), 0);
+    assert_eq!(tree.range(15..).map(NodeId::index).collect::<Vec<_>>(), vec![15, 16, 17, 18, 19]);
+    assert_eq!(tree.range(..3).map(NodeId::index).collect::<Vec<_>>(), vec![0, 1, 2]);
+    assert_eq!(tree.range(..=3).map(NodeId::index).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    assert_eq!(tree.range(..).map(NodeId::index).collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+    assert_eq!(tree.range(0..20).count(), 20);
+
+    // empty ranges
+    assert_eq!(tree.range(5..5).count(), 0);
+    assert_eq!(tree.range(20..30).count(), 0);
+    assert_eq!(tree.range((Bound::Excluded(3), Bound::Excluded(4))).count(), 0);
+
+    // absent bounds
+    let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+    let keys: Vec<i32> = tree.range(15..35).map(|i| 10 * (i.index() as i32 + 1)).collect();
+    assert_eq!(keys, vec![20, 30]);
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn drain_range_removes_the_range() {
+    let mut tree = SplayTree::new(20);
+    tree.splay(Some(id(12)));
+    assert_eq!(tree.drain_range(5..15).collect::<Vec<_>>(), (5..15).collect::<Vec<_>>());
+    assert_eq!(tree.len(), 10);
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(tree.clone().into_keys().collect::<Vec<_>>(), [0, 1, 2, 3, 4, 15, 16, 17, 18, 19]);
+
+    // dropping the iterator unconsumed still removes the keys
+    drop(tree.drain_range(..=2));
+    assert_eq!(tree.drain_range(17..).collect::<Vec<_>>(), vec![17, 18, 19]);
+    assert_eq!(tree.drain_range(10..12).count(), 0);
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(tree.clone().into_keys().collect::<Vec<_>>(), [3, 4, 15, 16]);
+
+    assert_eq!(tree.drain_range(..).count(), 4);
+    assert!(tree.is_empty());
+}
+
+#[test]
+fn count_range_matches_range() {
+    let mut seed = 0x2545f4914f6cdd1d_u64;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        seed
+    };
+    let mut tree = SplayTree::from_sorted_keys(Vec::new());
+    for _ in 0..300 {
+        tree.insert(next() % 500);
+    }
+    for _ in 0..200 {
+        let (lo, hi) = (next() % 520, next() % 520);
+        let expected = tree.range(lo..hi).count();
+        assert_eq!(tree.count_range(&lo, &hi), expected, "[{}, {})", lo, hi);
+    }
+    assert_eq!(tree.count_range(&0, &500), tree.len());
+    assert_eq!(SplayTree::new(0).count_range(&0, &10), 0);
+}
+
+#[test]
+fn clone_does_not_alias() {
+    let mut tree = SplayTree::from_sorted_pairs((0..10).map(|i| (i, i * i)).collect());
+    let mut copy = tree.clone();
+    tree.splay(Some(id(2)));
+    copy.splay(Some(id(7)));
+    *copy.value_mut(id(3)) = 0;
+
+    assert_ne!(format!("{:?}", tree), format!("{:?}", copy));
+    assert_eq!(tree.depth(id(2)), 0);
+    assert_eq!(copy.depth(id(7)), 0);
+    assert_eq!(*tree.value(id(3)), 9);
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(copy.check_invariants(), Ok(()));
+}
+
+#[test]
+fn to_dot_lists_nodes_and_edges() {
+    let mut tree = SplayTree::new(3);
+    tree.splay(Some(id(1)));
+    assert_eq!(tree.to_dot(), "digraph splay_tree {
+    node [shape=circle];
+    n0 [label=\"0\"];
+    n1 [label=\"1\", style=filled, fillcolor=lightblue];
+    n1 -> n0 [label=\"L\"];
+    n1 -> n2 [label=\"R\"];
+    n2 [label=\"2\"];
+}
+");
+
+    let mut words = SplayTree::from_sorted_keys(vec!["a\"b", "c"]);
+    words.remove(&"c");
+    let dot = words.to_dot();
+    assert!(dot.contains(r#"n0 [label="a\"b", style=filled"#));
+    assert!(!dot.contains("n1"));
+}
+
+#[test]
+fn into_iter_yields_sorted_order() {
+    let mut tree = SplayTree::new(0);
+    for k in [5, 2, 8, 1, 9, 3] {
+        tree.insert(k);
+    }
+    tree.remove(&8);
+    assert_eq!(tree.into_keys().collect::<Vec<_>>(), vec![1, 2, 3, 5, 9]);
+
+    let mut map = SplayTree::from_sorted_pairs(vec![(1, 'a'), (2, 'b'), (3, 'c')]);
+    map.splay(Some(id(1)));
+    let pairs: Vec<(i32, char)> = map.into_iter().collect();
+    assert_eq!(pairs, vec![(1, 'a'), (2, 'b'), (3, 'c')]);
+}
+
+#[test]
+fn iter_mut_doubles_values_in_order() {
+    let mut tree = SplayTree::from_sorted_pairs((0..10).map(|i| (i, i * 10)).collect());
+    tree.splay(Some(id(4)));
+    tree.remove(&7);
+    let root = tree.find(&4).unwrap();
+
+    let mut keys = Vec::new();
+    for (k, v) in tree.iter_mut() {
+        keys.push(*k);
+        *v *= 2;
+    }
+    assert_eq!(keys, vec![0, 1, 2, 3, 4, 5, 6, 8, 9]);
+    assert_eq!(tree.iter_mut().count(), 9);
+    assert_eq!(tree.depth(root), 0);
+    assert_eq!(tree.into_iter().collect::<Vec<_>>(), [0, 1, 2, 3, 4, 5, 6, 8, 9].map(|k| (k, k * 20)));
+}
+
+#[test]
+fn keys_and_values_in_sorted_order() {
+    let pairs = vec![(3, "c"), (1, "a"), (4, "d"), (2, "b")];
+    let mut tree = SplayTree::default();
+    for &(k, v) in &pairs {
+        tree.insert_pair(k, v);
+    }
+    let dump = format!("{:?}", tree);
+    assert_eq!(tree.keys().copied().collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+    assert_eq!(tree.values().copied().collect::<Vec<_>>(), vec!["a", "b", "c", "d"]);
+    assert_eq!(tree.iter().collect::<Vec<_>>(), vec![(&1, &"a"), (&2, &"b"), (&3, &"c"), (&4, &"d")]);
+    assert_eq!(tree.keys().size_hint(), (4, Some(4)));
+    assert_eq!(format!("{:?}", tree), dump);
+
+    for v in tree.values_mut() {
+        *v = if *v < "c" { "low" } else { "high" };
+    }
+    assert_eq!(tree.values().copied().collect::<Vec<_>>(), vec!["low", "low", "high", "high"]);
+    assert_eq!(SplayTree::new(0).keys().count(), 0);
+}
+
+#[test]
+fn dump_sorted_lists_inorder() {
+    let mut tree = SplayTree::new(10);
+    assert_eq!(tree.dump_sorted(), (0..10).map(id).collect::<Vec<_>>());
+    tree.splay(Some(id(5)));
+    tree.remove(&3);
+    assert_eq!(tree.dump_sorted_keys(), vec![0, 1, 2, 4, 5, 6, 7, 8, 9]);
+
+    let words = SplayTree::from_sorted_keys(vec!["b", "c"]);
+    let mut tree = SplayTree::default();
+    tree.insert("c");
+    tree.insert("a");
+    assert_eq!(tree.dump_sorted(), vec![id(1), id(0)]);
+    assert_eq!(words.dump_sorted_keys(), vec!["b", "c"]);
+}
+
+#[test]
+fn collect_sorted_and_unsorted() {
+    let sorted: SplayTree<i32> = (1..=5).collect();
+    assert_eq!(sorted.to_string(), "5\n  L: 4\n    L: 3\n      L: 2\n        L: 1\n");
+    assert_eq!(sorted.into_keys().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+
+    let unsorted: SplayTree<i32> = vec![4, 1, 5, 2, 4, 3].into_iter().collect();
+    assert_eq!(unsorted.len(), 5);
+    assert_eq!(unsorted.into_keys().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn rebalance_to_minimum_height() {
+    let mut tree = SplayTree::from_sorted_pairs((1..=7).map(|k| (k, k * 10)).collect());
+    tree.splay(Some(id(0)));
+    tree.rebalance();
+    assert_eq!(tree.to_string(), "4\n  L: 2\n    L: 1\n    R: 3\n  R: 6\n    L: 5\n    R: 7\n");
+    assert_eq!(*tree.value(id(3)), 40);
+
+    assert_eq!(tree.select(2).map(|i| *tree.key(i)), Some(3));
+    assert_eq!(tree.into_iter().collect::<Vec<_>>(), (1..=7).map(|k| (k, k * 10)).collect::<Vec<_>>());
+}
+
+#[test]
+fn height_and_depth() {
+    let mut tree = SplayTree::new(10);
+    assert_eq!(tree.height(), 9);
+    for i in 0..10 {
+        assert_eq!(tree.depth(id(i)), 9 - i);
+    }
+
+    tree.splay(Some(id(0)));
+    assert_eq!(tree.depth(id(0)), 0);
+    assert_eq!(tree.height(), 6);
+
+    tree.rebalance();
+    assert_eq!(tree.height(), 3);
+    assert_eq!(SplayTree::new(1).height(), 0);
+    assert_eq!(SplayTree::new(0).height(), 0);
+}
+
+#[test]
+fn bfs_is_level_order() {
+    let mut tree = SplayTree::new(7);
+    tree.rebalance();
+    assert_eq!(tree.bfs().collect::<Vec<_>>(), [3, 1, 5, 0, 2, 4, 6].map(id));
+    assert_eq!(tree.nth_from_root(0), Some(id(3)));
+    assert_eq!(tree.nth_from_root(4), Some(id(2)));
+    assert_eq!(tree.nth_from_root(7), None);
+
+    // a path comes out root first, one node per level
+    let path = SplayTree::new(4);
+    assert_eq!(path.bfs().collect::<Vec<_>>(), [3, 2, 1, 0].map(id));
+    assert_eq!(SplayTree::new(0).bfs().next(), None);
+}
+
+#[test]
+fn sorted_path_detection() {
+    let mut tree = SplayTree::new(10);
+    assert!(tree.is_sorted_path());
+    tree.splay(Some(id(0)));
+    assert!(!tree.is_sorted_path());
+    tree.rebalance();
+    assert!(!tree.is_sorted_path());
+
+    // splaying the keys in increasing order rebuilds the path
+    for i in 0..10 {
+        tree.splay(Some(id(i)));
+    }
+    assert!(tree.is_sorted_path());
+
+    // one zig-zig turns a 3-node left path into a right path
+    let mut right_path = SplayTree::from_sorted_keys(vec![1, 2, 3]);
+    right_path.splay(Some(id(0)));
+    assert_eq!(right_path.depth(id(2)), 2);
+    assert!(right_path.is_sorted_path());
+    assert!(SplayTree::new(0).is_sorted_path() && SplayTree::new(1).is_sorted_path());
+}
+
+#[test]
+fn depth_histogram() {
+    let mut tree = SplayTree::new(8);
+    assert_eq!(tree.balance_factor_histogram(), vec![1, 1, 1, 1, 1, 1, 1, 1]);
+
+    tree.splay(Some(id(0)));
+    let histogram = tree.balance_factor_histogram();
+    assert_eq!(histogram.len(), tree.height() + 1);
+    assert_eq!(histogram.iter().sum::<usize>(), 8);
+    assert_eq!(histogram[0], 1);
+
+    tree.rebalance();
+    assert_eq!(tree.balance_factor_histogram(), vec![1, 2, 4, 1]);
+    assert_eq!(SplayTree::new(0).balance_factor_histogram(), Vec::<usize>::new());
+}
+
+#[test]
+fn potential_of_path_and_balanced_tree() {
+    // sizes 1 through 7 down the path, against 7, 3, 3 and four leaves
+    let mut tree = SplayTree::new(7);
+    assert!((tree.potential() - 5040f64.log2()).abs() < 1e-9);
+    tree.rebalance();
+    assert!((tree.potential() - 63f64.log2()).abs() < 1e-9);
+    assert_eq!(SplayTree::new(0).potential(), 0.0);
+    assert_eq!(SplayTree::new(1).potential(), 0.0);
+}
+
+// One step of a random workload. Keys are drawn from a small range so that
+// inserts, removes and finds collide often, and a failing case shrinks
+// toward fewer ops and smaller keys.
+#[derive(Clone, Debug)]
+enum ModelOp {
+    Insert(u8),
+    Remove(u8),
+    Find(u8),
+    Select(u8),
+    PopMin,
+    PopMax
+}
+
+fn model_op() -> impl proptest::strategy::Strategy<Value = ModelOp> {
+    use proptest::prelude::*;
+    prop_oneof![
+        (0..32u8).prop_map(ModelOp::Insert),
+        (0..32u8).prop_map(ModelOp::Remove),
+        (0..32u8).prop_map(ModelOp::Find),
+        any::<u8>().prop_map(ModelOp::Select),
+        Just(ModelOp::PopMin),
+        Just(ModelOp::PopMax)
+    ]
+}
+
+proptest::proptest! {
+    #[test]
+    fn matches_btreeset_model(ops in proptest::collection::vec(model_op(), 0..200)) {
+        let mut tree = SplayTree::from_sorted_keys(Vec::new());
+        let mut model = BTreeSet::new();
+        for op in &ops {
+            match *op {
+                ModelOp::Insert(k) => {
+                    let idx = tree.insert(k);
+                    model.insert(k);
+                    proptest::prop_assert_eq!(*tree.key(idx), k);
+                },
+                ModelOp::Remove(k) => {
+                    proptest::prop_assert_eq!(tree.remove(&k), model.remove(&k));
+                },
+                ModelOp::Find(k) => {
+                    let found = tree.find(&k).map(|idx| *tree.key(idx));
+                    proptest::prop_assert_eq!(found, model.get(&k).copied());
+                },
+                ModelOp::Select(r) => {
+                    let r = r as usize % (model.len() + 1);
+                    let found = tree.select(r).map(|idx| *tree.key(idx));
+                    proptest::prop_assert_eq!(found, model.iter().nth(r).copied());
+                },
+                ModelOp::PopMin => proptest::prop_assert_eq!(tree.pop_min(), model.pop_first()),
+                ModelOp::PopMax => proptest::prop_assert_eq!(tree.pop_max(), model.pop_last())
+            }
+            proptest::prop_assert_eq!(tree.check_invariants(), Ok(()), "after {:?}", op);
+            proptest::prop_assert_eq!(tree.dump_sorted_keys(), model.iter().copied().collect::<Vec<_>>());
+        }
+    }
+}
+
+#[test]
+fn apply_ops_replays_a_sequence() {
+    use splay_tree::Op;
+
+    let ops = vec![
+        Op::Insert(5), Op::Insert(2), Op::Insert(8), Op::Find(2),
+        Op::Splay(id(2)), Op::Remove(5), Op::Insert(3), Op::Splay(id(0)),
+        Op::Splay(id(99)), Op::Remove(7), Op::Find(9)
+    ];
+    let mut tree = SplayTree::from_sorted_keys(Vec::new());
+    tree.apply_ops(&ops);
+    assert_eq!(tree.dump_sorted_keys(), vec![2, 3, 8]);
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    // the same ops give the same tree
+    let mut again = SplayTree::from_sorted_keys(Vec::new());
+    again.apply_ops(&ops);
+    assert_eq!(format!("{:?}", again), format!("{:?}", tree));
+}
+
+#[test]
+fn with_comparator_orders_by_the_closure() {
+    let mut tree = SplayTree::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+    for key in [3, 9, 1, 7, 5] {
+        tree.insert(key);
+    }
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(tree.dump_sorted_keys(), vec![9, 7, 5, 3, 1]);
+
+    // min is first in the comparator's order, the numerically largest key
+    let idx = tree.min().unwrap();
+    assert_eq!(*tree.key(idx), 9);
+    assert_eq!(tree.pop_max(), Some(1));
+    assert!(tree.contains(&7));
+    assert_eq!(tree.lower_bound(&6).map(|i| *tree.key(i)), Some(5));
+    // bounds are in the comparator's order too, so this range runs downward
+    let in_range: Vec<NodeId> = tree.range((Bound::Included(8), Bound::Included(4))).collect();
+    assert_eq!(in_range.iter().map(|&i| *tree.key(i)).collect::<Vec<_>>(), vec![7, 5]);
+
+    let (left, right) = tree.split(&5);
+    assert_eq!((left.dump_sorted_keys(), right.dump_sorted_keys()), (vec![9, 7, 5], vec![3]));
+    let tree = SplayTree::join(left, right);
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn with_comparator_on_a_derived_key() {
+    let mut tree = SplayTree::with_comparator(|a: &&str, b: &&str| a.len().cmp(&b.len()));
+    for (word, n) in [("ccc", 3), ("a", 1), ("bb", 2)] {
+        tree.insert_pair(word, n);
+    }
+    // "dd" has the same length as "bb", so the two are the same key
+    *tree.entry("dd").or_insert(0) += 10;
+    assert_eq!(tree.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(), vec![("a", 1), ("bb", 12), ("ccc", 3)]);
+}
+
+#[test]
+fn root_key_is_the_last_accessed() {
+    let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+    assert_eq!((tree.root_index(), tree.root_key()), (Some(id(3)), Some(&40)));
+
+    tree.find(&20);
+    assert_eq!((tree.root_index(), tree.root_key()), (Some(id(1)), Some(&20)));
+    tree.splay(Some(id(2)));
+    assert_eq!(tree.root_key(), Some(&30));
+
+    // a miss leaves the last node on the search path on top
+    tree.find(&15);
+    assert!(matches!(tree.root_key(), Some(&10) | Some(&20)));
+
+    let empty = SplayTree::new(0);
+    assert_eq!((empty.root_index(), empty.root_key()), (None, None));
+}
+
+// new(n) is a left path n deep, so any traversal that recursed on it would
+// overflow the stack long before reaching the bottom
+#[test]
+fn traversals_of_a_million_node_path() {
+    const N: usize = 1_000_000;
+    let mut tree = SplayTree::new(N);
+    assert_eq!(tree.height(), N - 1);
+    assert_eq!(tree.depth(id(0)), N - 1);
+    assert_eq!(tree.check_invariants(), Ok(()));
+
+    assert!(tree.iter().map(|(k, _)| *k).eq(0..N));
+    assert!(tree.keys().copied().eq(0..N));
+    assert_eq!(tree.iter_mut().count(), N);
+    assert_eq!(tree.dump_sorted().len(), N);
+
+    let copy = tree.clone();
+    assert!(copy == tree);
+    assert!(tree.into_iter().map(|(k, _)| k).eq(0..N));
+}
+
+#[test]
+fn split_at_index_positions() {
+    for k in [0, 1, 4, 9, 10, 15] {
+        let (left, right) = SplayTree::new(10).split_at_index(k);
+        let k = k.min(10);
+        assert_eq!((left.len(), right.len()), (k, 10 - k), "split at {}", k);
+        assert_eq!(left.dump_sorted_keys(), (0..k).collect::<Vec<_>>());
+        assert_eq!(right.dump_sorted_keys(), (k..10).collect::<Vec<_>>());
+        assert_eq!(left.check_invariants(), Ok(()));
+        assert_eq!(right.check_invariants(), Ok(()));
+    }
+
+    // position, not key: the third smallest of these keys is 30
+    let tree = SplayTree::from_sorted_keys(vec![10, 20, 30, 40]);
+    let (left, right) = tree.split_at_index(2);
+    assert_eq!((left.dump_sorted_keys(), right.dump_sorted_keys()), (vec![10, 20], vec![30, 40]));
+
+    let (left, right) = SplayTree::new(0).split_at_index(0);
+    assert!(left.is_empty() && right.is_empty());
+}
+
+#[test]
+fn concat_undoes_split_at_index() {
+    for k in [0, 3, 7, 10] {
+        let mut tree = SplayTree::new(10);
+        tree.splay(Some(id(4)));
+        let (mut left, right) = tree.split_at_index(k);
+        left.concat(right);
+        assert_eq!(left.dump_sorted_keys(), (0..10).collect::<Vec<_>>(), "split at {}", k);
+        assert_eq!(left.check_invariants(), Ok(()));
+    }
+
+    // no key order is needed across the seam; position is all that counts
+    let mut seq = SplayTree::from_sorted_keys(vec![50, 60]);
+    seq.concat(SplayTree::from_sorted_keys(vec![10, 20, 30]));
+    assert_eq!(seq.dump_sorted_keys(), vec![50, 60, 10, 20, 30]);
+    assert_eq!(seq.len(), 5);
+    let idx = seq.select(2).unwrap();
+    assert_eq!(*seq.key(idx), 10);
+    assert!(seq.check_invariants().is_err());
+}
+
+#[test]
+fn merge_sorted_into_matches_inserts() {
+    let mut merged = SplayTree::from_sorted_pairs((0..10).map(|k| (k, k * 2)).collect());
+    merged.find(&4);
+    let mut inserted = merged.clone();
+
+    merged.merge_sorted_into((10..100).map(|k| (k, k * 2)));
+    for k in 10..100 {
+        inserted.insert_pair(k, k * 2);
+    }
+    assert!(merged == inserted);
+    assert_eq!(merged.len(), 100);
+    assert_eq!(merged.check_invariants(), Ok(()));
+
+    // the batch is balanced, so the merged tree is much shallower than the
+    // one built by inserting in order
+    assert!(merged.height() < 20, "height {}", merged.height());
+
+    let mut empty = SplayTree::from_sorted_keys(Vec::new());
+    empty.merge_sorted_into([(1, ()), (2, ()), (3, ())]);
+    assert_eq!(empty.dump_sorted_keys(), vec![1, 2, 3]);
+    empty.merge_sorted_into([]);
+    assert_eq!(empty.check_invariants(), Ok(()));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "keys must increase and start above the tree's maximum")]
+fn merge_sorted_into_rejects_overlap() {
+    let mut tree = SplayTree::new(10);
+    tree.merge_sorted_into([(9, ()), (12, ())]);
+}
+
+#[test]
+fn vec_round_trip() {
+    let tree = SplayTree::from(vec![5, 1, 4, 1, 3]);
+    assert_eq!(tree.len(), 4);
+    assert_eq!(tree.check_invariants(), Ok(()));
+    assert_eq!(Vec::from(tree), vec![1, 3, 4, 5]);
+
+    let keys: Vec<i32> = (0..20).collect();
+    let mut tree: SplayTree<i32> = keys.clone().into();
+    tree.find(&7);
+    let back: Vec<i32> = tree.into();
+    assert_eq!(back, keys);
+
+    let mut reversed = SplayTree::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+    reversed.extend([1, 2, 3]);
+    assert_eq!(Vec::from(reversed), vec![3, 2, 1]);
+    assert_eq!(Vec::from(SplayTree::from(Vec::<i32>::new())), Vec::<i32>::new());
+}
+
+#[test]
+fn node_views_walk_the_tree() {
+    let mut tree = SplayTree::from_sorted_pairs((0..8).map(|k| (k, k * k)).collect());
+    tree.splay(Some(id(3)));
+
+    // inorder walk by views alone, checking each link from both ends
+    let root = tree.node(tree.root_index().unwrap());
+    assert_eq!((root.index(), root.key(), root.parent(), root.size()), (id(3), &3, None, 8));
+    let mut keys = Vec::new();
+    let mut stack = Vec::new();
+    let mut cur = Some(root.index());
+    while cur.is_some() || !stack.is_empty() {
+        while let Some(i) = cur {
+            stack.push(i);
+            cur = tree.node(i).left();
+        }
+        let view = tree.node(stack.pop().unwrap());
+        for child in [view.left(), view.right()].into_iter().flatten() {
+            assert_eq!(tree.node(child).parent(), Some(view.index()));
+        }
+        assert_eq!(*view.value(), view.key() * view.key());
+        keys.push(*view.key());
+        cur = view.right();
+    }
+    assert_eq!(keys, (0..8).collect::<Vec<_>>());
+}
+
+#[test]
+#[should_panic(expected = "node: 2 has been removed")]
+fn node_view_of_removed_node() {
+    let mut tree = SplayTree::new(4);
+    tree.remove(&2);
+    tree.node(id(2));
+}
+
+#[test]
+fn swap_values_keeps_keys() {
+    let mut tree = SplayTree::from_sorted_pairs(vec![(1, "one"), (2, "two"), (3, "three")]);
+    tree.splay(Some(id(1)));
+    let before = format!("{}", tree);
+
+    assert_eq!(tree.swap_values(id(0), id(2)), Ok(()));
+    assert_eq!((tree.key(id(0)), tree.value(id(0))), (&1, &"three"));
+    assert_eq!((tree.key(id(2)), tree.value(id(2))), (&3, &"one"));
+    assert_eq!(format!("{}", tree), before);
+    assert_eq!(tree.swap_values(id(1), id(1)), Ok(()));
+    assert_eq!(tree.value(id(1)), &"two");
+
+    assert_eq!(tree.swap_values(id(0), id(5)), Err(SplayError::OutOfRange { index: id(5), len: 3 }));
+    tree.remove(&2);
+    assert_eq!(tree.swap_values(id(1), id(0)), Err(SplayError::Removed { index: id(1) }));
+    assert_eq!(tree.value(id(0)), &"three");
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn contains_index_tracks_removal() {
+    let mut tree = SplayTree::from_sorted_keys(vec![10, 20, 30]);
+    let idx = tree.find(&20).unwrap();
+    assert!(tree.contains_index(idx));
+    tree.remove(&20);
+    assert!(!tree.contains_index(idx));
+    assert!(!tree.contains_index(id(3)));
+    assert!(tree.contains_index(id(0)) && tree.contains_index(id(2)));
+
+    // shrink_to_fit moves 30 down into the freed slot, past the end of the arena
+    tree.shrink_to_fit();
+    assert!(!tree.contains_index(id(2)));
+    assert_eq!(tree.find(&30), Some(id(1)));
+}
+
+#[test]
+fn splay_all_to_balanced_height() {
+    for n in [1, 2, 7, 100, 1000] {
+        let mut tree = SplayTree::new(n);
+        let height = tree.splay_all_to_balanced();
+        assert_eq!(height, tree.height());
+        assert_eq!(tree.check_invariants(), Ok(()));
+        assert_eq!(tree.dump_sorted_keys(), (0..n).collect::<Vec<_>>());
+
+        // medians all the way down: exactly the height of a complete tree
+        let log_n = usize::BITS - 1 - n.leading_zeros();
+        assert_eq!(height, log_n as usize, "new({})", n);
+    }
+    assert_eq!(SplayTree::new(0).splay_all_to_balanced(), 0);
+}
+
+#[test]
+fn equal_trees_dedup_in_a_hash_set() {
+    use std::collections::HashSet;
+
+    let path = SplayTree::new(16);
+    let mut splayed = SplayTree::new(16);
+    splayed.splay(Some(id(5)));
+    let mut balanced: SplayTree<usize> = (0..16).rev().collect();
+    balanced.rebalance();
+    assert_ne!(path.height(), splayed.height());
+
+    let mut set = HashSet::new();
+    assert!(set.insert(path));
+    assert!(!set.insert(splayed));
+    assert!(!set.insert(balanced));
+    assert!(set.insert(SplayTree::new(15)));
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn access_by_rank_reads_every_rank() {
+    let mut tree = SplayTree::from_sorted_pairs(Vec::new());
+    for k in [35, 3, 92, 14, 65, 15] {
+        tree.insert_pair(k, k.to_string());
+    }
+    let sorted = [3, 14, 15, 35, 65, 92];
+    for (k, key) in sorted.iter().enumerate() {
+        assert_eq!(tree.access_by_rank(k), Some((key, &key.to_string())));
+        assert_eq!(tree.root_key(), Some(key));
+    }
+    assert_eq!(tree.access_by_rank(6), None);
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+fn split_by_rank_every_position() {
+    for k in 0..=10 {
+        let mut tree = SplayTree::new(10);
+        tree.splay(Some(id(k % 10)));
+        let (left, right) = tree.split_by_rank(k);
+        assert_eq!((left.len(), right.len()), (k, 10 - k));
+        assert_eq!(left.dump_sorted_keys(), (0..k).collect::<Vec<_>>());
+        assert_eq!(right.dump_sorted_keys(), (k..10).collect::<Vec<_>>());
+        assert_eq!(left.check_invariants(), Ok(()));
+        assert_eq!(right.check_invariants(), Ok(()));
+    }
+    let (left, right) = SplayTree::new(3).split_by_rank(7);
+    assert_eq!((left.len(), right.len()), (3, 0));
+}
+
+#[test]
+fn map_values_keeps_keys_and_shape() {
+    let mut tree = SplayTree::from_sorted_pairs((0..10).map(|k| (k, k * 3)).collect());
+    tree.splay(Some(id(6)));
+    tree.remove(&2);
+    let shape = format!("{}", tree);
+
+    let mut seen = Vec::new();
+    let strings = tree.map_values(|v: i32| {
+        seen.push(v);
+        v.to_string()
+    });
+    assert_eq!(seen, vec![0, 3, 9, 12, 15, 18, 21, 24, 27]);
+    assert_eq!(format!("{}", strings), shape);
+    assert_eq!(strings.iter().map(|(k, v)| (*k, v.clone())).collect::<Vec<_>>(),
+        [0, 1, 3, 4, 5, 6, 7, 8, 9].map(|k| (k, (k * 3).to_string())));
+    assert_eq!(strings.value(id(6)), "18");
+    assert_eq!(strings.check_invariants(), Ok(()));
+}
+
+#[test]
+fn filter_map_into_keeps_even_keys() {
+    let mut tree = SplayTree::from_sorted_pairs((0..20).map(|k| (k, k * 10)).collect());
+    tree.splay(Some(id(7)));
+    let evens = tree.filter_map_into(|k, v| (k % 2 == 0).then_some((k, v * 2)));
+    assert_eq!(evens.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(),
+        (0..20).step_by(2).map(|k| (k, k * 20)).collect::<Vec<_>>());
+    assert_eq!(evens.len(), 10);
+    assert_eq!(evens.height(), 3);
+    assert_eq!(evens.check_invariants(), Ok(()));
+
+    // keys that come out of order or collide are sorted, and the later pair wins
+    let tree = SplayTree::from_sorted_pairs((0..6).map(|k| (k, k)).collect());
+    let folded = tree.filter_map_into(|k, v| Some((5 - k % 3, v)));
+    assert_eq!(folded.iter().map(|(k, v)| (*k, *v)).collect::<Vec<_>>(), vec![(3, 5), (4, 4), (5, 3)]);
+    assert_eq!(folded.check_invariants(), Ok(()));
+}
+
+#[test]
+fn parent_chain_walks_to_the_root() {
+    let mut tree = SplayTree::new(50);
+    for i in [17, 3, 41, 29, 8] {
+        tree.splay(Some(id(i)));
+    }
+    for i in (0..50).map(id) {
+        let chain = tree.parent_chain(i).collect::<Vec<_>>();
+        assert_eq!(chain.len(), tree.depth(i) + 1);
+        assert_eq!(chain[0], i);
+        assert_eq!(chain.last(), tree.root_index().as_ref());
+        for w in chain.windows(2) {
+            assert_eq!(tree.node(w[0]).parent(), Some(w[1]));
+        }
+    }
+    assert_eq!(tree.check_invariants(), Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "parent_chain: node index 9 out of range for tree of 4 nodes")]
+fn parent_chain_out_of_range() {
+    SplayTree::new(4).parent_chain(id(9));
+}
+
+#[test]
+#[should_panic(expected = "parent_chain: node index 3 refers to a removed node")]
+fn parent_chain_of_retained_away_node() {
+    let mut tree = SplayTree::new(8);
+    tree.retain(|&k| k % 2 == 0);
+    tree.parent_chain(id(3));
+}